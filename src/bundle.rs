@@ -0,0 +1,124 @@
+use crate::state::MachineState;
+use anyhow::anyhow;
+use std::{
+    fs,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+const HEADER: &str = ";i8086-decode crash bundle v2";
+
+/// Everything needed to reproduce a failed simulation.
+#[derive(Debug, Default)]
+pub(crate) struct CrashBundle {
+    pub(crate) args: Vec<String>,
+    pub(crate) error: String,
+    pub(crate) recent: Vec<String>,
+    pub(crate) state: MachineState,
+    pub(crate) image: Vec<u8>,
+    /// The `--load` images, with the physical address each was copied to.
+    pub(crate) loads: Vec<(u32, Vec<u8>)>,
+    pub(crate) restored: Option<MachineState>,
+}
+
+impl CrashBundle {
+    pub(crate) fn write_to(&self, path: &Path) -> anyhow::Result<()> {
+        let mut out = BufWriter::new(fs::File::create(path)?);
+        writeln!(out, "{HEADER}")?;
+        for line in self.error.lines() {
+            writeln!(out, "error: {line}")?;
+        }
+        for arg in &self.args {
+            writeln!(out, "arg: {arg}")?;
+        }
+        for line in &self.recent {
+            writeln!(out, "recent: {line}")?;
+        }
+        write_state(&mut out, "state", &self.state)?;
+        if let Some(restored) = &self.restored {
+            write_state(&mut out, "restored", restored)?;
+        }
+        for chunk in self.image.chunks(16) {
+            writeln!(out, "image: {}", hex(chunk))?;
+        }
+        // Each image starts a new row, so images that happen to touch stay apart
+        for (address, data) in &self.loads {
+            for (ix, chunk) in data.chunks(16).enumerate() {
+                let key = if ix == 0 { "load" } else { "load+" };
+                writeln!(
+                    out,
+                    "{key}: {:05x} {}",
+                    address + ix as u32 * 16,
+                    hex(chunk)
+                )?;
+            }
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    pub(crate) fn read_from(path: &Path) -> anyhow::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let mut lines = contents.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(anyhow!("{} is not a crash bundle", path.display()));
+        }
+
+        let mut bundle = Self::default();
+        let mut error = vec![];
+        let mut state = vec![];
+        let mut restored = vec![];
+        for line in lines {
+            let (key, value) = line
+                .split_once(": ")
+                .or_else(|| line.strip_suffix(':').map(|key| (key, "")))
+                .ok_or_else(|| anyhow!("malformed crash bundle line: {line}"))?;
+            match key {
+                "error" => error.push(value),
+                "arg" => bundle.args.push(value.to_string()),
+                "recent" => bundle.recent.push(value.to_string()),
+                "state" => state.push(value),
+                "restored" => restored.push(value),
+                "image" => bundle.image.extend(bytes(value)?),
+                "load" | "load+" => {
+                    let (address, data) = value
+                        .split_once(' ')
+                        .ok_or_else(|| anyhow!("malformed load row: {value}"))?;
+                    let data = bytes(data)?;
+                    match bundle.loads.last_mut() {
+                        Some((_, image)) if key == "load+" => image.extend(data),
+                        _ => bundle.loads.push((u32::from_str_radix(address, 16)?, data)),
+                    }
+                }
+                _ => return Err(anyhow!("unknown crash bundle section: {key}")),
+            }
+        }
+        bundle.error = error.join("\n");
+        bundle.state = MachineState::parse(state)?;
+        if !restored.is_empty() {
+            bundle.restored = Some(MachineState::parse(restored)?);
+        }
+        Ok(bundle)
+    }
+}
+
+fn write_state(out: &mut impl Write, key: &str, state: &MachineState) -> anyhow::Result<()> {
+    let mut lines = vec![];
+    state.write(&mut lines)?;
+    for line in String::from_utf8(lines)?.lines() {
+        writeln!(out, "{key}: {line}")?;
+    }
+    Ok(())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let hex: Vec<_> = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    hex.join(" ")
+}
+
+fn bytes(hex: &str) -> anyhow::Result<Vec<u8>> {
+    Ok(hex
+        .split_whitespace()
+        .map(|byte| u8::from_str_radix(byte, 16))
+        .collect::<Result<_, _>>()?)
+}
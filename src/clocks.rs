@@ -0,0 +1,337 @@
+use crate::{
+    instruction::{Inst, Mnemonic, Operand},
+    register::Register,
+    target::MemoryAddress,
+};
+use clap::ValueEnum;
+use derive_more::Display;
+
+/// The processor to estimate clocks for; the 8088 moves words over its 8-bit bus in halves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, ValueEnum)]
+pub(crate) enum Cpu {
+    #[display("8086")]
+    #[value(name = "8086")]
+    I8086,
+    #[display("8088")]
+    #[value(name = "8088")]
+    I8088,
+}
+
+impl Cpu {
+    pub(crate) fn transfer_penalty(self) -> u32 {
+        match self {
+            Cpu::I8086 => 0,
+            Cpu::I8088 => 4,
+        }
+    }
+}
+
+/// Estimated clock count for one instruction: base cost, effective address and word transfers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Clocks {
+    pub(crate) base: u32,
+    pub(crate) ea: u32,
+    pub(crate) transfers: u32,
+}
+
+impl Clocks {
+    pub(crate) fn total(&self, cpu: Cpu) -> u32 {
+        self.base + self.ea + self.transfers * cpu.transfer_penalty()
+    }
+
+    /// How the total splits up, e.g. `8 + 6ea + 4p`. `None` when it's all base cost.
+    pub(crate) fn breakdown(&self, cpu: Cpu) -> Option<String> {
+        let penalty = self.transfers * cpu.transfer_penalty();
+        if self.ea == 0 && penalty == 0 {
+            return None;
+        }
+        let mut out = self.base.to_string();
+        if self.ea > 0 {
+            out += &format!(" + {}ea", self.ea);
+        }
+        if penalty > 0 {
+            out += &format!(" + {penalty}p");
+        }
+        Some(out)
+    }
+}
+
+/// What the executor knows after running an instruction that its timing depends on.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Outcome {
+    pub(crate) jumped: bool,
+    pub(crate) shift_count: u16,
+    pub(crate) repetitions: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Acc,
+    Reg,
+    Seg,
+    Mem,
+    Imm,
+    Other,
+}
+
+fn kind(operand: &Option<Operand>) -> Kind {
+    match operand {
+        Some(Operand::Register(Register::AX | Register::AL)) => Kind::Acc,
+        Some(Operand::Register(Register::ES | Register::CS | Register::SS | Register::DS)) => {
+            Kind::Seg
+        }
+        Some(Operand::Register(_)) => Kind::Reg,
+        Some(Operand::MemoryAddress(_) | Operand::SizedMemory(_) | Operand::FarMemory(_)) => {
+            Kind::Mem
+        }
+        Some(Operand::Data(_) | Operand::DataArg(_)) => Kind::Imm,
+        _ => Kind::Other,
+    }
+}
+
+fn memory_address(operand: &Option<Operand>) -> Option<&MemoryAddress> {
+    match operand {
+        Some(Operand::MemoryAddress(m)) => Some(m),
+        Some(Operand::SizedMemory(m)) => Some(&m.address),
+        Some(Operand::FarMemory(m)) => Some(&m.0),
+        _ => None,
+    }
+}
+
+/// Clocks to compute an effective address, from the 8086 user's manual table.
+fn ea_clocks(address: &MemoryAddress) -> u32 {
+    use MemoryAddress::*;
+    use Register::*;
+    match address {
+        Direct(_) => 6,
+        Reg(_) => 5,
+        RegnData(..) => 9,
+        RegnReg(BP, DI) | RegnReg(BX, SI) => 7,
+        RegnReg(..) => 8,
+        RegnRegnData(BP, DI, _) | RegnRegnData(BX, SI, _) => 11,
+        RegnRegnData(..) => 12,
+    }
+}
+
+fn is_direct(operand: &Option<Operand>) -> bool {
+    matches!(memory_address(operand), Some(MemoryAddress::Direct(_)))
+}
+
+fn accesses_words(op1: &Option<Operand>, op2: &Option<Operand>) -> bool {
+    let width = |op: &Option<Operand>| match op {
+        Some(Operand::Register(r)) => Some(r.is_wide()),
+        Some(Operand::SizedMemory(m)) => Some(m.is_wide),
+        Some(Operand::DataArg(d)) if d.explicit => Some(d.data.is_wide()),
+        _ => None,
+    };
+    width(op1).or(width(op2)).unwrap_or(true)
+}
+
+/// The number of 16-bit memory or port transfers `inst` makes, which the 8088 splits in two.
+fn word_transfers(inst: &Inst, outcome: Outcome) -> u32 {
+    use Kind::*;
+    use Mnemonic::*;
+
+    let (op1, op2) = &inst.operands;
+    let (k1, k2) = (kind(op1), kind(op2));
+    let words = |n| if accesses_words(op1, op2) { n } else { 0 };
+    match inst.mnemonic {
+        Mov | Cmp | Test | Mul | Imul | Div | Idiv if k1 == Mem || k2 == Mem => words(1),
+        Add | Adc | Sub | Sbb | And | Or | Xor if k1 == Mem => words(2),
+        Add | Adc | Sub | Sbb | And | Or | Xor if k2 == Mem => words(1),
+        Inc | Dec | Neg | Not | Shl | Shr | Sar | Rol | Ror | Rcl | Rcr if k1 == Mem => words(2),
+        In | Out => words(1),
+        Push | Pop if k1 == Mem => 2,
+        Push | Pop | Pushf | Popf | Ret => 1,
+        Call => match op1 {
+            Some(Operand::FarMemory(_)) => 4,
+            Some(Operand::FarPointer(_)) => 2,
+            _ if k1 == Mem => 2,
+            _ => 1,
+        },
+        Jmp => match op1 {
+            Some(Operand::FarMemory(_)) => 2,
+            _ if k1 == Mem => 1,
+            _ => 0,
+        },
+        Retf | Lds | Les => 2,
+        Int | Int3 => 5,
+        Into if outcome.jumped => 5,
+        Iret => 3,
+        Movsw | Cmpsw => 2,
+        Scasw | Lodsw | Stosw => 1,
+        _ => 0,
+    }
+}
+
+fn is_wide(operand: &Option<Operand>) -> bool {
+    match operand {
+        Some(Operand::Register(r)) => r.is_wide(),
+        Some(Operand::SizedMemory(m)) => m.is_wide,
+        _ => true,
+    }
+}
+
+/// Estimates the clocks `inst` took; where the tables give a range, the lower bound.
+pub(crate) fn estimate(inst: &Inst, outcome: Outcome) -> Clocks {
+    use Kind::*;
+    use Mnemonic::*;
+
+    let (op1, op2) = &inst.operands;
+    let (k1, k2) = (kind(op1), kind(op2));
+    let taken = |yes, no| if outcome.jumped { yes } else { no };
+    // `mov` between the accumulator and a direct address has its own encoding with no EA
+    let accumulator_form = matches!(inst.mnemonic, Mov)
+        && ((k1 == Acc && is_direct(op2)) || (k2 == Acc && is_direct(op1)));
+    let base = match inst.mnemonic {
+        Mov => match (k1, k2) {
+            _ if accumulator_form => 10,
+            (Mem, Imm) => 10,
+            (_, Mem) => 8,
+            (Mem, _) => 9,
+            (_, Imm) => 4,
+            _ => 2,
+        },
+        Add | Adc | Sub | Sbb | And | Or | Xor => match (k1, k2) {
+            (Mem, Imm) => 17,
+            (Mem, _) => 16,
+            (_, Mem) => 9,
+            (_, Imm) => 4,
+            _ => 3,
+        },
+        Cmp => match (k1, k2) {
+            (Mem, Imm) => 10,
+            (Mem, _) | (_, Mem) => 9,
+            (_, Imm) => 4,
+            _ => 3,
+        },
+        Test => match (k1, k2) {
+            (Mem, Imm) => 11,
+            (Mem, _) | (_, Mem) => 9,
+            (Acc, Imm) => 4,
+            (_, Imm) => 5,
+            _ => 3,
+        },
+        Inc | Dec => match k1 {
+            Mem => 15,
+            _ if is_wide(op1) => 2,
+            _ => 3,
+        },
+        Neg | Not => match k1 {
+            Mem => 16,
+            _ => 3,
+        },
+        Mul | Imul | Div | Idiv => {
+            let (byte, word) = match inst.mnemonic {
+                Mul => (70, 118),
+                Imul => (80, 128),
+                Div => (80, 144),
+                _ => (101, 165),
+            };
+            let memory = if k1 == Mem { 6 } else { 0 };
+            (if is_wide(op1) { word } else { byte }) + memory
+        }
+        Shl | Shr | Sar | Rol | Ror | Rcl | Rcr => {
+            let by_cl = matches!(op2, Some(Operand::Register(Register::CL)));
+            match (k1, by_cl) {
+                (Mem, false) => 15,
+                (Mem, true) => 20 + 4 * outcome.shift_count as u32,
+                (_, false) => 2,
+                (_, true) => 8 + 4 * outcome.shift_count as u32,
+            }
+        }
+        Push => match k1 {
+            Mem => 16,
+            Seg => 10,
+            _ => 11,
+        },
+        Pop => match k1 {
+            Mem => 17,
+            _ => 8,
+        },
+        Pushf => 10,
+        Popf => 8,
+        Lea => 2,
+        Lds | Les => 16,
+        Lahf | Sahf => 4,
+        Xlat => 11,
+        In | Out => match (k1, k2) {
+            (Imm, _) | (_, Imm) => 10,
+            _ => 8,
+        },
+        Call => match op1 {
+            Some(Operand::RelativeJump(_)) => 19,
+            Some(Operand::FarPointer(_)) => 28,
+            Some(Operand::FarMemory(_)) => 37,
+            _ if k1 == Mem => 21,
+            _ => 16,
+        },
+        Jmp => match op1 {
+            Some(Operand::RelativeJump(_) | Operand::FarPointer(_)) => 15,
+            Some(Operand::FarMemory(_)) => 24,
+            _ if k1 == Mem => 18,
+            _ => 11,
+        },
+        Ret => match k1 {
+            Other => 8,
+            _ => 12,
+        },
+        Retf => match k1 {
+            Other => 18,
+            _ => 17,
+        },
+        Je | Jnz | Jl | Jnl | Jle | Jg | Jb | Jnb | Jbe | Ja | Jp | Jnp | Jo | Jno | Js | Jns => {
+            taken(16, 4)
+        }
+        Loop => taken(17, 5),
+        Loopz => taken(18, 6),
+        Loopnz => taken(19, 5),
+        Jcxz => taken(18, 6),
+        Int => 51,
+        Int3 => 52,
+        Into => taken(53, 4),
+        Iret => 24,
+        Aaa | Aas | Daa | Das => 4,
+        Aam => 83,
+        Aad => 60,
+        Movsb | Movsw => 18,
+        Cmpsb | Cmpsw => 22,
+        Scasb | Scasw => 15,
+        Lodsb | Lodsw => 12,
+        Stosb | Stosw => 11,
+        Esc if memory_address(op2).is_some() => 8,
+        Esc => 2,
+        Hlt | Clc | Cmc | Stc | Cld | Std | Cli | Sti => 2,
+    };
+    // A `rep` prefix replaces the single-shot cost with a setup cost plus one per repetition
+    let (base, transfers) = match (inst.rep, repeated_cost(inst.mnemonic)) {
+        (Some(_), Some(each)) => {
+            let n = outcome.repetitions as u32;
+            (9 + each * n, word_transfers(inst, outcome) * n)
+        }
+        _ => (base, word_transfers(inst, outcome)),
+    };
+    let ea = memory_address(op1)
+        .or(memory_address(op2))
+        .filter(|_| !accumulator_form)
+        .map(|address| ea_clocks(address) + if inst.segment.is_some() { 2 } else { 0 })
+        .unwrap_or_default();
+    Clocks {
+        base,
+        ea,
+        transfers,
+    }
+}
+
+/// Clocks per repetition of a `rep`-prefixed string instruction.
+fn repeated_cost(mnemonic: Mnemonic) -> Option<u32> {
+    use Mnemonic::*;
+    match mnemonic {
+        Movsb | Movsw => Some(17),
+        Cmpsb | Cmpsw => Some(22),
+        Scasb | Scasw => Some(15),
+        Lodsb | Lodsw => Some(13),
+        Stosb | Stosw => Some(10),
+        _ => None,
+    }
+}
@@ -1,26 +1,74 @@
 use crate::{
-    ByteStream, Inst, Mnemonic, data,
+    ByteStream, Inst, Mnemonic,
+    clocks::{self, Clocks, Cpu, Outcome},
+    data::{self, FarPointer, create_word},
+    flagcheck::FlagChecker,
     instruction::Operand,
-    register::{RegType, Register},
+    instruction::Rep,
+    iobus::{IoBus, UnconnectedBus},
+    memory::{Memory, MemoryAccess, MemoryRange},
+    register::{RegType, Register, RegisterValue},
+    state::MachineState,
+    target::MemoryAddress,
 };
 use anyhow::anyhow;
 use bitflags::bitflags;
 use enum_iterator::all;
 use std::{
     fmt::{self, Display, Write},
-    io::{Read, Seek},
+    io::{BufReader, Cursor},
     mem::take,
 };
 
+/// The newest trace format. Version 1 is the trace as the course prints it: each instruction
+/// with the registers and flags it changed, then the final registers and flags. Version 2 adds
+/// clock estimates, the pointer an `lds` or `les` loaded, flag warnings in the trace, and the
+/// stack, summary, instruction counts, console output and exit code after the final registers.
+pub(crate) const FORMAT_VERSION: u32 = 2;
+
+/// How many bytes to fetch at CS:IP for decoding: the longest instruction is six bytes, which
+/// leaves room for a generous run of prefixes.
+const MAX_INSTRUCTION_LEN: u16 = 16;
+
+/// How long a `hlt` with interrupts enabled waits for a device to interrupt before giving up,
+/// in clocks: a few of the PIT's longest periods.
+const HALT_WAIT_CLOCKS: u32 = 1 << 20;
+/// How many clocks pass between checks for an interrupt while halted.
+const HALT_WAIT_STEP: u32 = 64;
+
 bitflags! {
-    #[derive(Debug, Clone, Copy)]
-    struct Flags: u16 {
-        const Carry = 0b000001;
-        const Parity = 0b000010;
-        const AuxCarry = 0b000100;
-        const Sign = 0b001000;
-        const Zero = 0b010000;
-        const Overflow = 0b100000;
+    /// The 8086 FLAGS register, using the hardware bit positions.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub(crate) struct Flags: u16 {
+        const Carry = 1 << 0;
+        const Parity = 1 << 2;
+        const AuxCarry = 1 << 4;
+        const Sign = 1 << 7;
+        const Zero = 1 << 6;
+        const Trap = 1 << 8;
+        const Interrupt = 1 << 9;
+        const Direction = 1 << 10;
+        const Overflow = 1 << 11;
+    }
+}
+
+impl Flags {
+    /// The status flags set by arithmetic, as opposed to the control flags (TF, IF, DF).
+    pub(crate) const ARITHMETIC: Flags = Flags::Carry
+        .union(Flags::Parity)
+        .union(Flags::AuxCarry)
+        .union(Flags::Zero)
+        .union(Flags::Sign)
+        .union(Flags::Overflow);
+
+    /// The FLAGS word as `pushf` stores it; the 8086 reads the reserved bit 1 and the top
+    /// nibble back as ones.
+    pub(crate) fn to_word(self) -> u16 {
+        self.bits() | 0xF002
+    }
+
+    pub(crate) fn from_word(word: u16) -> Self {
+        Flags::from_bits_truncate(word)
     }
 }
 
@@ -33,6 +81,9 @@ impl Display for Flags {
                 f if f.contains(Flags::Parity) => 'P',
                 f if f.contains(Flags::Sign) => 'S',
                 f if f.contains(Flags::Zero) => 'Z',
+                f if f.contains(Flags::Trap) => 'T',
+                f if f.contains(Flags::Interrupt) => 'I',
+                f if f.contains(Flags::Direction) => 'D',
                 f if f.contains(Flags::Overflow) => 'O',
                 _ => unreachable!(),
             })?;
@@ -42,12 +93,78 @@ impl Display for Flags {
 }
 
 #[derive(Debug)]
-pub(crate) struct Computer<T> {
-    program: ByteStream<T>,
+pub(crate) struct Computer {
+    /// The loaded image; running off its end into empty memory ends the program.
+    program: Vec<u8>,
+    /// Where the program was loaded and starts; every segment register starts at its segment.
+    entry: FarPointer,
+    /// Everything copied into memory before running, so `reset` can put it back.
+    images: Vec<(u32, Vec<u8>)>,
+    /// The saved state the run was resumed from, which `reset` resumes from again.
+    restored: Option<MachineState>,
     registers: [u16; 12],
+    ip: u16,
+    /// Where the instruction being executed started.
+    instruction_ip: u16,
+    /// Values given to registers before the first instruction.
+    initial_registers: Vec<RegisterValue>,
+    memory: Memory,
     flags: Flags,
     last_update: Update,
     print_ip: bool,
+    /// Which trace format to render; see `FORMAT_VERSION`.
+    format_version: u32,
+    flag_checker: Option<FlagChecker>,
+    watched_registers: Vec<Register>,
+    watched_memory: Vec<MemoryRange>,
+    /// Report every memory access in the update, not just watched ones.
+    log_accesses: bool,
+    io: Box<dyn IoBus>,
+    dump_stack: bool,
+    /// Abort rather than run more than this many instructions.
+    instruction_limit: Option<u64>,
+    instructions_executed: u64,
+    /// Trap after every instruction regardless of TF, as a debugger stepping the program would.
+    single_step: bool,
+    /// The CPUs to estimate clocks for, alongside each one's running total.
+    clock_totals: Vec<(Cpu, u64)>,
+    /// Per-mnemonic counts for the final report, in the order each first ran.
+    mnemonic_stats: Option<Vec<MnemonicStats>>,
+    /// Text written through the emulated DOS and BIOS services.
+    console: Vec<u8>,
+    /// Set once the program asks DOS to terminate it.
+    exit_code: Option<u8>,
+    /// Set by `hlt` or program termination; no further instructions run.
+    halted: bool,
+    /// The current instruction's segment override prefix, if it has one.
+    segment_override: Option<Register>,
+    /// Where the stack started, so the dump knows how many words are live. Follows any
+    /// write to SP that isn't itself a push or pop.
+    stack_top: u16,
+    /// Undo records for every instruction run so far, when stepping backwards is enabled.
+    journal: Option<Vec<JournalEntry>>,
+}
+
+/// How often one mnemonic ran and the clocks it accounted for on each CPU being estimated.
+#[derive(Debug)]
+struct MnemonicStats {
+    mnemonic: Mnemonic,
+    count: u64,
+    clocks: Vec<u64>,
+}
+
+/// What one instruction changed, recorded so it can be undone.
+#[derive(Debug)]
+struct JournalEntry {
+    ip: u16,
+    flags: Flags,
+    /// Register file slots the instruction wrote, with their earlier values.
+    registers: Vec<(usize, u16)>,
+    /// Memory the instruction stored to, with the earlier values, in the order written.
+    writes: Vec<(u32, bool, u16)>,
+    stack_top: u16,
+    console_len: usize,
+    exit_code: Option<u8>,
 }
 
 #[derive(Debug)]
@@ -71,19 +188,36 @@ impl Display for RegUpdate {
 
 #[derive(Debug, Default)]
 pub(crate) struct Update {
-    reg_update: Option<RegUpdate>,
+    reg_updates: Vec<RegUpdate>,
     flag_update: Option<(Flags, Flags)>,
-    ip_update: Option<(u64, u64)>,
+    ip_update: Option<(u16, u16)>,
+    /// The `segment:offset` pair an `lds` or `les` loaded, with the registers it went into.
+    loaded_pointer: Option<(Register, Register, FarPointer)>,
+    clocks: Option<(Clocks, Vec<(Cpu, u64)>)>,
+    watch_hits: Vec<(Register, u16, u16)>,
+    memory_hits: Vec<MemoryAccess>,
+    accesses: Vec<MemoryAccess>,
+    single_stepped: bool,
+    /// Why the instruction's use of the flags looks wrong, when flag warnings are enabled.
+    flag_warning: Option<Box<str>>,
 }
 
 impl Update {
     pub(crate) fn print(&self, print_ip: bool) -> Result<String, fmt::Error> {
         let mut parts = vec![];
 
-        if let Some(reg) = &self.reg_update {
+        for reg in &self.reg_updates {
             parts.push(reg.to_string());
         }
 
+        if let Some((segment, offset, pointer)) = &self.loaded_pointer {
+            parts.push(format!(
+                "{}:{}={pointer:#}",
+                segment.as_wide_str(),
+                offset.as_wide_str()
+            ));
+        }
+
         if print_ip && let Some((from, to)) = &self.ip_update {
             parts.push(format!("ip:{from:#x}->{to:#x}"));
         }
@@ -92,7 +226,53 @@ impl Update {
             parts.push(format!("flags:{from}->{to}"));
         }
 
-        Ok(parts.join(" "))
+        let changes = parts.join(" ");
+        let Some((clocks, totals)) = &self.clocks else {
+            return Ok(changes);
+        };
+        let estimates: Vec<_> = totals
+            .iter()
+            .map(|(cpu, total)| {
+                let mut estimate = match totals.len() {
+                    1 => format!("Clocks: +{} = {total}", clocks.total(*cpu)),
+                    _ => format!("{cpu} clocks: +{} = {total}", clocks.total(*cpu)),
+                };
+                if let Some(breakdown) = clocks.breakdown(*cpu) {
+                    estimate += &format!(" ({breakdown})");
+                }
+                estimate
+            })
+            .collect();
+        let mut out = estimates.join(" | ");
+        if !changes.is_empty() {
+            write!(out, " | {changes}")?;
+        }
+        Ok(out)
+    }
+
+    /// Whether the instruction ended in a single-step trap that no INT 1 handler took.
+    pub(crate) fn single_stepped(&self) -> bool {
+        self.single_stepped
+    }
+
+    /// Every load and store the instruction made, if the computer was asked to log them.
+    pub(crate) fn accesses(&self) -> &[MemoryAccess] {
+        &self.accesses
+    }
+
+    pub(crate) fn flag_warning(&self) -> Option<&str> {
+        self.flag_warning.as_deref()
+    }
+
+    pub(crate) fn watch_hits(&self) -> impl Iterator<Item = String> {
+        self.watch_hits
+            .iter()
+            .map(|(reg, from, to)| format!("watch {reg}: {from:#x}->{to:#x}"))
+            .chain(
+                self.memory_hits
+                    .iter()
+                    .map(|access| format!("watch {access}")),
+            )
     }
 }
 
@@ -102,161 +282,1695 @@ pub(crate) enum ExeResult {
     Success(Inst, Update),
 }
 
-impl<T: Read + Seek> Computer<T> {
-    pub(crate) fn new(program: ByteStream<T>, print_ip: bool) -> Self {
-        Self {
-            program,
+impl Computer {
+    /// Loads `program` into memory at `entry`, where execution starts.
+    pub(crate) fn new(program: &[u8], entry: FarPointer, print_ip: bool) -> Self {
+        let mut computer = Self {
+            program: program.to_vec(),
+            entry,
+            images: vec![],
+            restored: None,
             registers: [0; 12],
+            ip: 0,
+            instruction_ip: 0,
+            initial_registers: vec![],
+            memory: Memory::new(),
             flags: Flags::empty(),
             last_update: Update::default(),
             print_ip,
+            format_version: FORMAT_VERSION,
+            flag_checker: None,
+            watched_registers: vec![],
+            watched_memory: vec![],
+            log_accesses: false,
+            io: Box::new(UnconnectedBus),
+            dump_stack: false,
+            instruction_limit: None,
+            instructions_executed: 0,
+            single_step: false,
+            clock_totals: vec![],
+            mnemonic_stats: None,
+            console: vec![],
+            exit_code: None,
+            halted: false,
+            segment_override: None,
+            stack_top: 0,
+            journal: None,
+        };
+        computer.enter();
+        let address = computer.physical_address(Register::CS, entry.offset);
+        computer.with_memory_image(address, program)
+    }
+
+    pub(crate) fn with_format_version(mut self, version: u32) -> Self {
+        self.format_version = version;
+        self
+    }
+
+    pub(crate) fn with_flag_warnings(mut self, enabled: bool) -> Self {
+        self.flag_checker = enabled.then(FlagChecker::default);
+        self
+    }
+
+    pub(crate) fn with_watched_registers(mut self, registers: Vec<Register>) -> Self {
+        self.watched_registers = registers;
+        self
+    }
+
+    pub(crate) fn with_watched_memory(mut self, ranges: Vec<MemoryRange>) -> Self {
+        self.watched_memory = ranges;
+        self
+    }
+
+    pub(crate) fn with_access_log(mut self, enabled: bool) -> Self {
+        self.log_accesses = enabled;
+        self
+    }
+
+    pub(crate) fn with_io_bus(mut self, bus: impl IoBus + 'static) -> Self {
+        self.io = Box::new(bus);
+        self
+    }
+
+    /// Copies `image` into memory starting at physical address `address`.
+    pub(crate) fn with_memory_image(mut self, address: u32, image: &[u8]) -> Self {
+        self.load_image(address, image);
+        self.images.push((address, image.to_vec()));
+        self
+    }
+
+    /// Points CS:IP at the program and the other segment registers at its segment.
+    fn enter(&mut self) {
+        self.registers = [0; 12];
+        for segment in [Register::CS, Register::DS, Register::ES, Register::SS] {
+            self.registers[segment.get_reg_ix()] = self.entry.segment;
+        }
+        self.ip = self.entry.offset;
+    }
+
+    fn load_image(&mut self, address: u32, image: &[u8]) {
+        for (ix, byte) in image.iter().enumerate() {
+            self.memory.write_byte(address + ix as u32, *byte);
         }
     }
 
+    pub(crate) fn with_initial_registers(mut self, values: Vec<RegisterValue>) -> Self {
+        self.initial_registers = values;
+        self.seed_registers();
+        self
+    }
+
+    /// Gives the registers their initial values without reporting it as a change.
+    fn seed_registers(&mut self) {
+        for RegisterValue { register, value } in self.initial_registers.clone() {
+            self.update_register(register, value);
+        }
+        self.stack_top = self.get_register(Register::SP);
+        self.last_update = Update::default();
+    }
+
+    pub(crate) fn with_clocks(mut self, cpus: Vec<Cpu>) -> Self {
+        self.clock_totals = cpus.into_iter().map(|cpu| (cpu, 0)).collect();
+        self
+    }
+
+    pub(crate) fn with_mnemonic_stats(mut self, enabled: bool) -> Self {
+        self.mnemonic_stats = enabled.then(Vec::new);
+        self
+    }
+
+    pub(crate) fn with_journal(mut self, enabled: bool) -> Self {
+        self.journal = enabled.then(Vec::new);
+        self
+    }
+
+    pub(crate) fn with_instruction_limit(mut self, limit: Option<u64>) -> Self {
+        self.instruction_limit = limit;
+        self
+    }
+
+    pub(crate) fn with_single_step(mut self, enabled: bool) -> Self {
+        self.single_step = enabled;
+        self
+    }
+
+    pub(crate) fn with_stack_dump(mut self, enabled: bool) -> Self {
+        self.dump_stack = enabled;
+        self
+    }
+
+    /// Puts the machine back the way it was before the first instruction: registers, flags,
+    /// memory and devices are cleared, the program and any other images are reloaded, IP is
+    /// rewound and any restored state is applied again. Options set with the `with_*` methods
+    /// are kept.
+    pub(crate) fn reset(&mut self) {
+        self.enter();
+        self.flags = Flags::empty();
+        self.memory = Memory::new();
+        for (address, image) in take(&mut self.images) {
+            self.load_image(address, &image);
+            self.images.push((address, image));
+        }
+        self.last_update = Update::default();
+        if self.flag_checker.is_some() {
+            self.flag_checker = Some(FlagChecker::default());
+        }
+        self.instructions_executed = 0;
+        for (_, total) in &mut self.clock_totals {
+            *total = 0;
+        }
+        if let Some(stats) = &mut self.mnemonic_stats {
+            stats.clear();
+        }
+        self.console.clear();
+        self.io.reset();
+        self.exit_code = None;
+        self.halted = false;
+        self.segment_override = None;
+        if let Some(journal) = &mut self.journal {
+            journal.clear();
+        }
+        self.seed_registers();
+        if let Some(state) = self.restored.clone() {
+            self.apply_state(state);
+        }
+    }
+
+    pub(crate) fn ip(&self) -> u16 {
+        self.ip
+    }
+
+    pub(crate) fn flags(&self) -> Flags {
+        self.flags
+    }
+
+    pub(crate) fn exit_code(&self) -> Option<u8> {
+        self.exit_code
+    }
+
+    /// Changes a register from outside the program, e.g. from a debugger, without it showing up
+    /// in the next trace line.
+    pub(crate) fn set_register(&mut self, reg: Register, value: u16) {
+        self.update_register(reg, value);
+        self.last_update = Update::default();
+    }
+
+    pub(crate) fn set_ip(&mut self, ip: u16) {
+        self.ip = ip;
+    }
+
+    pub(crate) fn set_flags_word(&mut self, word: u16) {
+        self.flags = Flags::from_word(word);
+    }
+
+    /// Every register, IP and the flags on one line, zero or not.
+    pub(crate) fn snapshot(&self) -> String {
+        let mut parts: Vec<_> = all::<Register>()
+            .filter(|r| r.is_wide())
+            .map(|r| format!("{r}={:#06x}", self.get_register(r)))
+            .collect();
+        parts.push(format!("ip={:#06x}", self.ip));
+        parts.push(format!("flags={}", self.flags));
+        parts.join(" ")
+    }
+
+    /// Captures everything needed to resume the program later.
+    pub(crate) fn save_state(&self) -> anyhow::Result<MachineState> {
+        Ok(MachineState {
+            registers: self.registers,
+            ip: self.ip,
+            flags: self.flags.bits(),
+            memory: self.memory.region(0..Memory::SIZE)?.to_vec(),
+            instructions_executed: self.instructions_executed,
+            stack_top: self.stack_top,
+            console: self.console.clone(),
+            exit_code: self.exit_code,
+            halted: self.halted,
+        })
+    }
+
+    /// Picks up from a saved state, replacing the machine's registers, flags and memory.
+    pub(crate) fn restore_state(&mut self, state: MachineState) {
+        self.restored = Some(state.clone());
+        self.apply_state(state);
+    }
+
+    fn apply_state(&mut self, state: MachineState) {
+        self.registers = state.registers;
+        self.ip = state.ip;
+        self.flags = Flags::from_word(state.flags);
+        self.load_image(0, &state.memory);
+        self.instructions_executed = state.instructions_executed;
+        self.stack_top = state.stack_top;
+        self.console = state.console;
+        self.exit_code = state.exit_code;
+        self.halted = state.halted;
+        self.last_update = Update::default();
+    }
+
+    pub(crate) fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    pub(crate) fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+
     pub(crate) fn execute_instruction(&mut self) -> anyhow::Result<ExeResult> {
         use Mnemonic::*;
-        use Operand::*;
 
-        let ip_before = self.program.get_iptr()?;
-        let Some(i) = Inst::parse(&mut self.program)? else {
-            self.print_registers(ip_before);
+        if self.halted {
             return Ok(ExeResult::Halt);
-        };
-        let ip_after = self.program.get_iptr()?;
+        }
+        let ip_before = self.ip;
+        self.instruction_ip = ip_before;
+        // Outside its image a program runs whatever is in memory, so code it wrote there or
+        // loaded with `--load` runs too. Empty memory right where the image ends is how a program
+        // without a `hlt` finishes; anywhere else it means control went astray
+        let cs = self.get_register(Register::CS);
+        let end = self.entry.offset as usize + self.program.len();
+        let in_image = cs == self.entry.segment
+            && (self.entry.offset as usize..end).contains(&(ip_before as usize));
+        if !in_image && self.is_blank(ip_before) {
+            if cs == self.entry.segment && ip_before as usize == end {
+                return Ok(ExeResult::Halt);
+            }
+            return Err(anyhow!(
+                "ran into empty memory at {cs:04x}:{ip_before:04x}, outside the program"
+            ));
+        }
+        let (i, len) = self.decode_at(ip_before)?;
+        if let Some(limit) = self.instruction_limit
+            && self.instructions_executed >= limit
+        {
+            return Err(anyhow!(
+                "instruction limit of {limit} reached before `{i}` at ip {ip_before:#x}; \
+                 the program may be stuck in a loop"
+            ));
+        }
+        if let Some(journal) = &mut self.journal {
+            journal.push(JournalEntry {
+                ip: ip_before,
+                flags: self.flags,
+                registers: vec![],
+                writes: vec![],
+                stack_top: self.stack_top,
+                console_len: self.console.len(),
+                exit_code: self.exit_code,
+            });
+        }
+        self.ip = ip_before.wrapping_add(len);
+        self.instructions_executed += 1;
+        let ip_after = self.ip;
         self.update_ip(ip_before, ip_after);
-        let Inst { mnemonic, operands } = &i;
+        if let Some(checker) = &mut self.flag_checker {
+            self.last_update.flag_warning = checker
+                .check(i.mnemonic, ip_before.into())
+                .map(String::into_boxed_str);
+        }
+        let Inst {
+            mnemonic, segment, ..
+        } = &i;
+        self.segment_override = *segment;
+        let shift_count = self.get_register(crate::Register::CL);
+        // The 8086 traps after an instruction that started with TF set, so the `popf` that sets
+        // it runs one more instruction first
+        let trap = self.flags.contains(Flags::Trap);
+        let mut repetitions = 0;
+        // Whether a conditional transfer was taken; a taken jump can land where it started
+        let mut jumped = false;
 
-        let (Some(dest), source) = &operands else {
-            todo!("Haven't implemented: {i} => {:?}", i)
-        };
         match mnemonic {
-            Mov => match (dest, source) {
-                (Register(r), Some(Data(d))) => self.update_register(*r, d.into()),
-                (Register(r1), Some(Register(r2))) => {
-                    self.update_register(*r1, self.get_register(*r2))
-                }
-                _ => todo!("Haven't implemented: {i} => {:?}", i),
-            },
-            Sub | Cmp | Add => {
-                let op: fn(u16, u16) -> (u16, Flags) = match mnemonic {
-                    Add => |a, b| {
-                        let mut flags = Flags::empty();
-                        flags.set(Flags::Carry, a.checked_add(b).is_none());
-                        flags.set(Flags::AuxCarry, (((a & 0xF) + (b & 0xF)) & 0xFFF0) != 0);
-                        flags.set(Flags::Overflow, (a as i16).checked_add(b as i16).is_none());
-                        (a.wrapping_add(b), flags)
-                    },
-                    Sub | Cmp => |a, b| {
-                        let mut flags = Flags::empty();
-                        flags.set(Flags::Carry, a.checked_sub(b).is_none());
-                        flags.set(
-                            Flags::AuxCarry,
-                            ((((a & 0xF) | 0x10) - (b & 0xF)) & 0xFFF0) == 0,
-                        );
-                        flags.set(Flags::Overflow, (a as i16).checked_sub(b as i16).is_none());
-                        (a.wrapping_sub(b), flags)
-                    },
-                    _ => unreachable!(),
-                };
-                let Register(r) = dest else {
-                    return Err(anyhow!("invalid destination operand for {i}"));
+            Mov => {
+                let (dest, source, is_wide) = binary_operands(&i)?;
+                let value = self.read_operand(source, is_wide)?;
+                self.write_operand(dest, is_wide, value)?;
+            }
+            Add | Adc | Sub | Sbb | Cmp => {
+                let op = match mnemonic {
+                    Add | Adc => add,
+                    _ => subtract,
                 };
-                let res = self.do_op(
-                    r,
-                    match source {
-                        Some(Register(r)) => self.get_register(*r),
-                        Some(DataArg(d)) => (&d.data).into(),
-                        Some(Data(d)) => d.into(),
-                        _ => Err(anyhow!("invalid source operand for {i}"))?,
-                    },
-                    op,
-                );
+                let carry_in = matches!(mnemonic, Adc | Sbb) && self.flags.contains(Flags::Carry);
+                let (dest, source, is_wide) = binary_operands(&i)?;
+                let a = self.read_operand(dest, is_wide)?;
+                let b = self.read_operand(source, is_wide)?;
+                let res = self.do_op(a, b, carry_in, is_wide, op);
 
                 if !matches!(mnemonic, Cmp) {
-                    self.update_register(*r, res);
+                    self.write_operand(dest, is_wide, res)?;
                 }
             }
-            Jnz => {
-                if !self.flags.contains(Flags::Zero) {
-                    let Some(RelativeJump(data::RelativeJump { offset })) = operands.0 else {
-                        return Err(anyhow!("invalid operand for {i}"));
-                    };
-                    self.program
-                        .set_iptr(offset as i64 - (ip_after - ip_before) as i64)?;
-                    let ip_after = self.program.get_iptr()?;
-                    self.update_ip(ip_before, ip_after);
+            Je | Jnz | Jl | Jnl | Jle | Jg | Jb | Jnb | Jbe | Ja | Jp | Jnp | Jo | Jno | Js
+            | Jns | Jcxz => {
+                jumped = self.condition_holds(*mnemonic);
+                if jumped {
+                    self.jump_relative(&i, ip_before)?;
                 }
             }
-            _ => todo!("Haven't implemented: {i} => {:?}", i),
+            Loop | Loopz | Loopnz => {
+                let cx = self.get_register(crate::Register::CX).wrapping_sub(1);
+                self.update_register(crate::Register::CX, cx);
+                jumped = cx != 0 && self.condition_holds(*mnemonic);
+                if jumped {
+                    self.jump_relative(&i, ip_before)?;
+                }
+            }
+            Aaa | Aas | Daa | Das => self.decimal_adjust(*mnemonic),
+            Push => {
+                let Some(operand) = &i.operands.0 else {
+                    return Err(anyhow!("invalid operands for {i}"));
+                };
+                // The 8086 decrements SP before reading the operand, so `push sp` stores the
+                // new value.
+                let sp = self.get_register(crate::Register::SP).wrapping_sub(2);
+                self.update_register(crate::Register::SP, sp);
+                let value = self.read_operand(operand, true)?;
+                self.push_word_at(sp, value);
+            }
+            Pop => {
+                let Some(operand) = &i.operands.0 else {
+                    return Err(anyhow!("invalid operands for {i}"));
+                };
+                let value = self.pop_word();
+                self.write_operand(operand, true, value)?;
+            }
+            Hlt => self.halted = true,
+            Mul | Imul | Div | Idiv => {
+                let Some(operand) = &i.operands.0 else {
+                    return Err(anyhow!("invalid operands for {i}"));
+                };
+                let is_wide = operand_width(operand)
+                    .ok_or_else(|| anyhow!("can't tell operand width of {i}"))?;
+                let source = self.read_operand(operand, is_wide)?;
+                match mnemonic {
+                    Mul | Imul => self.multiply(*mnemonic == Imul, source, is_wide),
+                    _ => {
+                        if !self.divide(*mnemonic == Idiv, source, is_wide) {
+                            self.interrupt(ip_before, 0, ip_after)?;
+                        }
+                    }
+                }
+            }
+            Inc | Dec => {
+                let Some(operand) = &i.operands.0 else {
+                    return Err(anyhow!("invalid operands for {i}"));
+                };
+                let is_wide = operand_width(operand)
+                    .ok_or_else(|| anyhow!("can't tell operand width of {i}"))?;
+                let value = self.read_operand(operand, is_wide)?;
+                let op = if *mnemonic == Inc { add } else { subtract };
+                // Unlike add and sub, inc and dec leave CF alone
+                let carry = self.flags.contains(Flags::Carry);
+                let (result, mut flags) = op(value, 1, false, is_wide);
+                flags.set(Flags::Carry, carry);
+                self.update_flags(result, flags, is_wide);
+                self.write_operand(operand, is_wide, result)?;
+            }
+            Neg => {
+                let Some(operand) = &i.operands.0 else {
+                    return Err(anyhow!("invalid operands for {i}"));
+                };
+                let is_wide = operand_width(operand)
+                    .ok_or_else(|| anyhow!("can't tell operand width of {i}"))?;
+                let value = self.read_operand(operand, is_wide)?;
+                // Subtracting from zero borrows, and so sets CF, for every operand but zero
+                let result = self.do_op(0, value, false, is_wide, subtract);
+                self.write_operand(operand, is_wide, result)?;
+            }
+            And | Or | Xor | Test => {
+                let (dest, source, is_wide) = binary_operands(&i)?;
+                let a = self.read_operand(dest, is_wide)?;
+                let b = self.read_operand(source, is_wide)?;
+                let result = match mnemonic {
+                    And | Test => a & b,
+                    Or => a | b,
+                    _ => a ^ b,
+                };
+                let flags = self.flags.difference(Flags::Carry | Flags::Overflow);
+                self.update_flags(result, flags, is_wide);
+                if *mnemonic != Test {
+                    self.write_operand(dest, is_wide, result)?;
+                }
+            }
+            Not => {
+                let Some(operand) = &i.operands.0 else {
+                    return Err(anyhow!("invalid operands for {i}"));
+                };
+                let is_wide = operand_width(operand)
+                    .ok_or_else(|| anyhow!("can't tell operand width of {i}"))?;
+                let value = self.read_operand(operand, is_wide)?;
+                self.write_operand(operand, is_wide, !value)?;
+            }
+            Shl | Shr | Sar | Rol | Ror | Rcl | Rcr => {
+                let (dest, count, is_wide) = binary_operands(&i)?;
+                let count = self.read_operand(count, false)?;
+                let value = self.read_operand(dest, is_wide)?;
+                let result = self.shift(*mnemonic, value, count, is_wide);
+                self.write_operand(dest, is_wide, result)?;
+            }
+            Movsb | Movsw | Cmpsb | Cmpsw | Scasb | Scasw | Lodsb | Lodsw | Stosb | Stosw => {
+                repetitions = self.string_operation(&i)?;
+            }
+            Jmp => self.jump_near(&i, ip_before)?,
+            Call => {
+                // Resolve the target before SP moves, so `call sp` uses the old value.
+                let sp = self.get_register(crate::Register::SP).wrapping_sub(2);
+                self.jump_near(&i, ip_before)?;
+                self.update_register(crate::Register::SP, sp);
+                self.push_word_at(sp, ip_after);
+            }
+            Ret => {
+                let ip = self.pop_word();
+                if let Some(Operand::Data(d)) = &i.operands.0 {
+                    let sp = self
+                        .get_register(crate::Register::SP)
+                        .wrapping_add(d.into());
+                    self.update_register(crate::Register::SP, sp);
+                }
+                self.jump_absolute(ip_before, ip);
+            }
+            Pushf => self.push_word(self.flags.to_word()),
+            Popf => {
+                let word = self.pop_word();
+                self.set_flags(Flags::from_word(word));
+            }
+            Int | Int3 => {
+                let vector = match &i.operands.0 {
+                    Some(Operand::Data(d)) => u16::from(d) as u8,
+                    _ => 3,
+                };
+                match vector {
+                    0x10 if !self.has_handler(vector) => self.bios_video_service()?,
+                    // DOS's program terminate, which a .COM reaches by returning to its PSP
+                    0x20 if !self.has_handler(vector) => {
+                        self.exit_code = Some(0);
+                        self.halted = true;
+                    }
+                    0x21 if !self.has_handler(vector) => self.dos_service()?,
+                    _ => self.interrupt(ip_before, vector, ip_after)?,
+                }
+            }
+            Into => {
+                jumped = self.flags.contains(Flags::Overflow);
+                if jumped {
+                    self.interrupt(ip_before, 4, ip_after)?;
+                }
+            }
+            Iret => {
+                let ip = self.pop_word();
+                let cs = self.pop_word();
+                let flags = self.pop_word();
+                self.update_register(crate::Register::CS, cs);
+                self.set_flags(Flags::from_word(flags));
+                self.jump_absolute(ip_before, ip);
+            }
+            In => {
+                let (Some(Operand::Register(acc)), Some(port)) = &i.operands else {
+                    return Err(anyhow!("invalid operands for {i}"));
+                };
+                let port = self.read_operand(port, true)?;
+                let value = self.port_in(port, acc.is_wide());
+                self.update_register(*acc, value);
+            }
+            Out => {
+                let (Some(port), Some(Operand::Register(acc))) = &i.operands else {
+                    return Err(anyhow!("invalid operands for {i}"));
+                };
+                let port = self.read_operand(port, true)?;
+                let value = self.get_register(*acc);
+                self.port_out(port, acc.is_wide(), value);
+            }
+            Lds | Les => {
+                let (Some(Operand::Register(reg)), Some(Operand::MemoryAddress(m))) = &i.operands
+                else {
+                    return Err(anyhow!("invalid operands for {i}"));
+                };
+                let segment = match mnemonic {
+                    Lds => crate::Register::DS,
+                    _ => crate::Register::ES,
+                };
+                // Both words come from the operand's segment before either register changes
+                let address = self.effective_address(m)?;
+                let pointer = FarPointer {
+                    offset: self.load(address, true),
+                    segment: self.load(address + 2, true),
+                };
+                self.update_register(*reg, pointer.offset);
+                self.update_register(segment, pointer.segment);
+                if self.format_version >= 2 {
+                    self.last_update.loaded_pointer = Some((segment, *reg, pointer));
+                }
+            }
+            Xlat => {
+                // AL indexes, unsigned, a byte table at BX in DS unless the segment is overridden
+                let segment = self.segment_override.unwrap_or(crate::Register::DS);
+                let offset = self
+                    .get_register(crate::Register::BX)
+                    .wrapping_add(self.get_register(crate::Register::AL));
+                let value = self.load(self.physical_address(segment, offset), false);
+                self.update_register(crate::Register::AL, value);
+            }
+            Lahf => {
+                let low = self.flags.to_word() as u8;
+                self.update_register(crate::Register::AH, low.into());
+            }
+            Sahf => {
+                let ah = Flags::from_word(self.get_register(crate::Register::AH));
+                let status = Flags::ARITHMETIC.difference(Flags::Overflow);
+                self.set_flags(self.flags.difference(status).union(ah.intersection(status)));
+            }
+            Clc | Stc | Cmc | Cld | Std | Cli | Sti => {
+                let mut flags = self.flags;
+                match mnemonic {
+                    Clc => flags.remove(Flags::Carry),
+                    Stc => flags.insert(Flags::Carry),
+                    Cmc => flags.toggle(Flags::Carry),
+                    Cld => flags.remove(Flags::Direction),
+                    Std => flags.insert(Flags::Direction),
+                    Cli => flags.remove(Flags::Interrupt),
+                    Sti => flags.insert(Flags::Interrupt),
+                    _ => unreachable!(),
+                }
+                self.set_flags(flags);
+            }
+            _ => return Err(anyhow!("haven't implemented: {i} => {i:?}")),
         };
+        let outcome = Outcome {
+            jumped,
+            shift_count,
+            repetitions,
+        };
+        let clocks = clocks::estimate(&i, outcome);
+        // Devices keep time by the first CPU being estimated, or the 8086 when none is
+        let cpu = self
+            .clock_totals
+            .first()
+            .map_or(Cpu::I8086, |(cpu, _)| *cpu);
+        self.io.tick(clocks.total(cpu));
+        if !self.clock_totals.is_empty() {
+            for (cpu, total) in &mut self.clock_totals {
+                *total += clocks.total(*cpu) as u64;
+            }
+            if self.format_version >= 2 {
+                self.last_update.clocks = Some((clocks, self.clock_totals.clone()));
+            }
+        }
+        if let Some(stats) = &mut self.mnemonic_stats {
+            let ix = match stats.iter().position(|s| s.mnemonic == *mnemonic) {
+                Some(ix) => ix,
+                None => {
+                    stats.push(MnemonicStats {
+                        mnemonic: *mnemonic,
+                        count: 0,
+                        clocks: vec![0; self.clock_totals.len()],
+                    });
+                    stats.len() - 1
+                }
+            };
+            stats[ix].count += 1;
+            if let Some((clocks, _)) = &self.last_update.clocks {
+                for (total, (cpu, _)) in stats[ix].clocks.iter_mut().zip(&self.clock_totals) {
+                    *total += clocks.total(*cpu) as u64;
+                }
+            }
+        }
+        // Loading SS holds off interrupts for one instruction so the SP load that follows can't
+        // be split from it, and `sti` takes effect only after the next instruction, which is
+        // what lets `sti; hlt` wait without an interrupt slipping in between
+        let loads_ss = matches!(mnemonic, Mov | Pop)
+            && matches!(i.operands.0, Some(Operand::Register(crate::Register::SS)));
+        let trap = trap && !loads_ss;
+        if self.single_step || (trap && self.flags.contains(Flags::Trap) && !self.halted) {
+            if !self.single_step && self.has_handler(1) {
+                self.interrupt(ip_before, 1, self.ip)?;
+            } else {
+                self.last_update.single_stepped = true;
+            }
+        }
+        if self.flags.contains(Flags::Interrupt) && !loads_ss && *mnemonic != Sti {
+            self.hardware_interrupt(ip_before)?;
+        }
+        if !matches!(
+            mnemonic,
+            Push | Pop | Pushf | Popf | Call | Ret | Int | Int3 | Into | Iret
+        ) && let Some(sp) = self
+            .last_update
+            .reg_updates
+            .iter()
+            .rfind(|u| matches!(u.reg, crate::Register::SP))
+        {
+            self.stack_top = sp.to_val;
+        }
+        if let Some(entry) = self.journal.as_mut().and_then(|journal| journal.last_mut()) {
+            entry.registers = self
+                .last_update
+                .reg_updates
+                .iter()
+                .map(|u| (u.reg.get_reg_ix(), u.from_val))
+                .collect();
+        }
         Ok(ExeResult::Success(i, take(&mut self.last_update)))
     }
 
-    fn do_op(&mut self, a: &Register, b: u16, op: fn(u16, u16) -> (u16, Flags)) -> u16 {
-        let a = self.get_register(*a);
-        let (res, flags) = op(a, b);
-        self.update_flags(res, flags);
+    /// Undoes the most recent instruction, putting back the registers, flags and memory it
+    /// changed. Port output and anything else outside the machine stays done. Returns false when
+    /// there's nothing left to undo.
+    pub(crate) fn step_back(&mut self) -> bool {
+        let Some(entry) = self.journal.as_mut().and_then(Vec::pop) else {
+            return false;
+        };
+        for (slot, value) in entry.registers {
+            self.registers[slot] = value;
+        }
+        for (address, is_wide, value) in entry.writes.into_iter().rev() {
+            self.memory.write(address, is_wide, value);
+        }
+        self.ip = entry.ip;
+        self.flags = entry.flags;
+        self.stack_top = entry.stack_top;
+        self.console.truncate(entry.console_len);
+        self.exit_code = entry.exit_code;
+        self.halted = false;
+        self.instructions_executed -= 1;
+        true
+    }
+
+    /// Enters the handler for `vector` from the interrupt vector table at the bottom of memory,
+    /// leaving FLAGS, CS and the return IP on the stack for `iret`. A vector still at 0000:0000
+    /// has no handler, and taking it is an error.
+    fn interrupt(&mut self, ip_before: u16, vector: u8, return_ip: u16) -> anyhow::Result<()> {
+        if !self.has_handler(vector) {
+            return Err(anyhow!(
+                "interrupt {vector:#04x} at ip {ip_before:#x} has no handler installed"
+            ));
+        }
+        let entry = vector as u32 * 4;
+        let ip = self.load(entry, true);
+        let cs = self.load(entry + 2, true);
+
+        self.push_word(self.flags.to_word());
+        self.push_word(self.get_register(crate::Register::CS));
+        self.push_word(return_ip);
+        self.set_flags(self.flags.difference(Flags::Interrupt | Flags::Trap));
+        self.update_register(crate::Register::CS, cs);
+        self.jump_absolute(ip_before, ip);
+        Ok(())
+    }
+
+    /// Takes an interrupt a device is requesting, if there is one. A `hlt` waits for one, the
+    /// way the 8086 sits halted until an interrupt arrives, and resumes after it; with no
+    /// handler installed the interrupt is acknowledged and ended, so it doesn't stay in service.
+    fn hardware_interrupt(&mut self, ip_before: u16) -> anyhow::Result<()> {
+        if self.exit_code.is_some() {
+            return Ok(());
+        }
+        let mut vector = self.io.interrupt();
+        let mut waited = 0;
+        while vector.is_none() && self.halted && waited < HALT_WAIT_CLOCKS {
+            self.io.tick(HALT_WAIT_STEP);
+            waited += HALT_WAIT_STEP;
+            vector = self.io.interrupt();
+        }
+        let Some(vector) = vector else {
+            return Ok(());
+        };
+        self.halted = false;
+        if self.has_handler(vector) {
+            self.interrupt(ip_before, vector, self.ip)?;
+        } else {
+            self.io.end_interrupt(vector);
+        }
+        Ok(())
+    }
+
+    /// Decodes the instruction at CS:`ip` without running it, returning it and its length.
+    pub(crate) fn decode_at(&self, ip: u16) -> anyhow::Result<(Inst, u16)> {
+        let mut fetched = self.fetch(ip);
+        let i =
+            Inst::parse(&mut fetched)?.ok_or_else(|| anyhow!("no instruction at ip {ip:#x}"))?;
+        Ok((i, fetched.get_iptr()? as u16))
+    }
+
+    /// Whether CS:`ip` holds the two zero bytes memory starts out as.
+    fn is_blank(&self, ip: u16) -> bool {
+        (0..2).all(|n| {
+            let address = self.physical_address(Register::CS, ip.wrapping_add(n));
+            self.memory.read_byte(address) == 0
+        })
+    }
+
+    /// The bytes at CS:`ip`, read from memory so code the program wrote at runtime runs as
+    /// written. The offset wraps within the code segment.
+    fn fetch(&self, ip: u16) -> ByteStream<Cursor<Vec<u8>>> {
+        let bytes = (0..MAX_INSTRUCTION_LEN)
+            .map(|n| {
+                let address = self.physical_address(Register::CS, ip.wrapping_add(n));
+                self.memory.read_byte(address)
+            })
+            .collect();
+        ByteStream {
+            reader: BufReader::new(Cursor::new(bytes)),
+        }
+    }
+
+    /// Shifts or rotates `value` `count` times a bit at a time, the way the 8086 does. CF holds
+    /// the last bit shifted out; shifts also set SF, ZF and PF from the result.
+    fn shift(&mut self, mnemonic: Mnemonic, value: u16, count: u16, is_wide: bool) -> u16 {
+        use Mnemonic::*;
+        if count == 0 {
+            return value;
+        }
+        let (mask, sign) = width_masks(is_wide);
+        let mut result = value & mask;
+        let mut carry = self.flags.contains(Flags::Carry);
+        let mut overflow = false;
+        for _ in 0..count {
+            let before = result;
+            let (msb, lsb) = (result & sign != 0, result & 1 != 0);
+            result = match mnemonic {
+                Shl => result << 1,
+                Shr => result >> 1,
+                Sar => result >> 1 | result & sign,
+                Rol => result << 1 | msb as u16,
+                Ror => result >> 1 | if lsb { sign } else { 0 },
+                Rcl => result << 1 | carry as u16,
+                Rcr => result >> 1 | if carry { sign } else { 0 },
+                _ => unreachable!(),
+            } & mask;
+            carry = match mnemonic {
+                Shl | Rol | Rcl => msb,
+                _ => lsb,
+            };
+            let top = result & sign != 0;
+            overflow = match mnemonic {
+                Shl | Rol | Rcl => top != carry,
+                Shr => before & sign != 0,
+                Sar => false,
+                _ => top != (result & sign >> 1 != 0),
+            };
+        }
+        let mut flags = self.flags;
+        flags.set(Flags::Carry, carry);
+        flags.set(Flags::Overflow, overflow);
+        if matches!(mnemonic, Shl | Shr | Sar) {
+            self.update_flags(result, flags, is_wide);
+        } else {
+            self.set_flags(flags);
+        }
+        result
+    }
+
+    /// `mul`/`imul`: AL or AX times `source`, into AX or DX:AX. CF and OF report whether the
+    /// upper half holds anything beyond the lower half's extension.
+    fn multiply(&mut self, signed: bool, source: u16, is_wide: bool) {
+        use crate::Register::{AL, AX, DX};
+        let (low, high) = if is_wide {
+            let a = self.get_register(AX);
+            let product = if signed {
+                (a as i16 as i32 * source as i16 as i32) as u32
+            } else {
+                a as u32 * source as u32
+            };
+            self.update_register(AX, product as u16);
+            self.update_register(DX, (product >> 16) as u16);
+            (
+                product as u16 as i16 as i32,
+                (product >> 16) as u16 as i16 as i32,
+            )
+        } else {
+            let a = self.get_register(AL);
+            let product = if signed {
+                (a as u8 as i8 as i16 * source as u8 as i8 as i16) as u16
+            } else {
+                a * source
+            };
+            self.update_register(AX, product);
+            (
+                product as u8 as i8 as i32,
+                (product >> 8) as u8 as i8 as i32,
+            )
+        };
+        let overflow = if signed { high != low >> 31 } else { high != 0 };
+        let mut flags = self.flags;
+        flags.set(Flags::Carry | Flags::Overflow, overflow);
+        self.set_flags(flags);
+    }
+
+    /// `div`/`idiv`: AX or DX:AX by `divisor`, leaving the quotient in AL or AX and the
+    /// remainder in AH or DX. Returns false, changing nothing, when the CPU would raise a divide
+    /// error instead.
+    fn divide(&mut self, signed: bool, divisor: u16, is_wide: bool) -> bool {
+        use crate::Register::{AH, AL, AX, DX};
+        if divisor == 0 {
+            return false;
+        }
+        let (quotient, remainder) = match (is_wide, signed) {
+            (true, false) => {
+                let dividend = (self.get_register(DX) as u32) << 16 | self.get_register(AX) as u32;
+                let quotient = dividend / divisor as u32;
+                if quotient > 0xFFFF {
+                    return false;
+                }
+                (quotient as u16, (dividend % divisor as u32) as u16)
+            }
+            (true, true) => {
+                let dividend =
+                    ((self.get_register(DX) as u32) << 16 | self.get_register(AX) as u32) as i32;
+                let divisor = divisor as i16 as i32;
+                let Some(quotient) = dividend.checked_div(divisor) else {
+                    return false;
+                };
+                if !(-0x7FFF..=0x7FFF).contains(&quotient) {
+                    return false;
+                }
+                (quotient as u16, (dividend % divisor) as u16)
+            }
+            (false, false) => {
+                let dividend = self.get_register(AX);
+                let divisor = divisor & 0xFF;
+                let quotient = dividend / divisor;
+                if quotient > 0xFF {
+                    return false;
+                }
+                (quotient, dividend % divisor)
+            }
+            (false, true) => {
+                let dividend = self.get_register(AX) as i16;
+                let divisor = divisor as u8 as i8 as i16;
+                let Some(quotient) = dividend.checked_div(divisor) else {
+                    return false;
+                };
+                if !(-0x7F..=0x7F).contains(&quotient) {
+                    return false;
+                }
+                (quotient as u16, (dividend % divisor) as u16)
+            }
+        };
+        if is_wide {
+            self.update_register(AX, quotient);
+            self.update_register(DX, remainder);
+        } else {
+            self.update_register(AL, quotient);
+            self.update_register(AH, remainder);
+        }
+        true
+    }
+
+    /// Runs a string instruction, once or for as long as its `rep` prefix says, stepping SI and
+    /// DI forwards or backwards according to DF. Returns how many times it ran.
+    fn string_operation(&mut self, i: &Inst) -> anyhow::Result<u16> {
+        use crate::Register::{AL, AX, CX, DI, DS, ES, SI};
+        use Mnemonic::*;
+
+        let is_wide = matches!(i.mnemonic, Movsw | Cmpsw | Scasw | Lodsw | Stosw);
+        let accumulator = if is_wide { AX } else { AL };
+        let size = if is_wide { 2 } else { 1 };
+        let step = if self.flags.contains(Flags::Direction) {
+            0u16.wrapping_sub(size)
+        } else {
+            size
+        };
+        let uses_source = matches!(i.mnemonic, Movsb | Movsw | Cmpsb | Cmpsw | Lodsb | Lodsw);
+        let uses_destination = !matches!(i.mnemonic, Lodsb | Lodsw);
+        let compares = matches!(i.mnemonic, Cmpsb | Cmpsw | Scasb | Scasw);
+        // Only the source segment can be overridden; the destination is always ES:DI
+        let source_segment = self.segment_override.unwrap_or(DS);
+
+        let mut count = 0;
+        loop {
+            if i.rep.is_some() && self.get_register(CX) == 0 {
+                break;
+            }
+            let (si, di) = (self.get_register(SI), self.get_register(DI));
+            let source = self.physical_address(source_segment, si);
+            let destination = self.physical_address(ES, di);
+            match i.mnemonic {
+                Movsb | Movsw => {
+                    let value = self.load(source, is_wide);
+                    self.store(destination, is_wide, value);
+                }
+                Cmpsb | Cmpsw => {
+                    let a = self.load(source, is_wide);
+                    let b = self.load(destination, is_wide);
+                    self.do_op(a, b, false, is_wide, subtract);
+                }
+                Scasb | Scasw => {
+                    let a = self.get_register(accumulator);
+                    let b = self.load(destination, is_wide);
+                    self.do_op(a, b, false, is_wide, subtract);
+                }
+                Lodsb | Lodsw => {
+                    let value = self.load(source, is_wide);
+                    self.update_register(accumulator, value);
+                }
+                Stosb | Stosw => {
+                    let value = self.get_register(accumulator);
+                    self.store(destination, is_wide, value);
+                }
+                _ => unreachable!(),
+            }
+            if uses_source {
+                self.update_register(SI, si.wrapping_add(step));
+            }
+            if uses_destination {
+                self.update_register(DI, di.wrapping_add(step));
+            }
+            count += 1;
+
+            let Some(rep) = i.rep else {
+                break;
+            };
+            self.update_register(CX, self.get_register(CX).wrapping_sub(1));
+            let zero = self.flags.contains(Flags::Zero);
+            if compares && (rep == Rep::Rep) != zero {
+                break;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Reads a byte from `port`, or a word from it and the port after, low byte first.
+    fn port_in(&mut self, port: u16, is_wide: bool) -> u16 {
+        let low = self.io.port_in(port);
+        if !is_wide {
+            return low.into();
+        }
+        create_word(low, self.io.port_in(port.wrapping_add(1)))
+    }
+
+    fn port_out(&mut self, port: u16, is_wide: bool, value: u16) {
+        let [low, high] = value.to_le_bytes();
+        self.io.port_out(port, low);
+        if is_wide {
+            self.io.port_out(port.wrapping_add(1), high);
+        }
+    }
+
+    /// Whether the program has pointed `vector` somewhere other than 0000:0000.
+    fn has_handler(&self, vector: u8) -> bool {
+        let entry = vector as u32 * 4;
+        self.memory.read_word(entry) != 0 || self.memory.read_word(entry + 2) != 0
+    }
+
+    /// Stands in for the BIOS video services; only teletype output is supported.
+    fn bios_video_service(&mut self) -> anyhow::Result<()> {
+        use crate::Register::{AH, AL};
+        match self.get_register(AH) {
+            0x0E => self.console.push(self.get_register(AL) as u8),
+            ah => return Err(anyhow!("unsupported BIOS video service: ah={ah:#04x}")),
+        }
+        Ok(())
+    }
+
+    /// Stands in for DOS when a program calls INT 21h without installing its own handler.
+    fn dos_service(&mut self) -> anyhow::Result<()> {
+        use crate::Register::{AH, AL, DL, DS, DX};
+        match self.get_register(AH) {
+            0x02 => self.console.push(self.get_register(DL) as u8),
+            0x09 => {
+                // DOS stops at the end of the segment rather than wrapping round to its start
+                let start = self.get_register(DX);
+                let text: Vec<_> = (start..=u16::MAX)
+                    .map(|offset| self.memory.read_byte(self.physical_address(DS, offset)))
+                    .collect();
+                let Some(len) = text.iter().position(|&b| b == b'$') else {
+                    return Err(anyhow!(
+                        "string at ds:{start:#x} has no '$' terminator before the segment ends"
+                    ));
+                };
+                self.console.extend(&text[..len]);
+            }
+            0x4C => {
+                self.exit_code = Some(self.get_register(AL) as u8);
+                self.halted = true;
+            }
+            ah => return Err(anyhow!("unsupported DOS service: ah={ah:#04x}")),
+        }
+        Ok(())
+    }
+
+    fn push_word(&mut self, value: u16) {
+        let sp = self.get_register(crate::Register::SP).wrapping_sub(2);
+        self.update_register(crate::Register::SP, sp);
+        self.push_word_at(sp, value);
+    }
+
+    fn push_word_at(&mut self, sp: u16, value: u16) {
+        let address = self.physical_address(crate::Register::SS, sp);
+        self.store(address, true, value);
+    }
+
+    fn pop_word(&mut self) -> u16 {
+        let sp = self.get_register(crate::Register::SP);
+        let value = self.load(self.physical_address(crate::Register::SS, sp), true);
+        self.update_register(crate::Register::SP, sp.wrapping_add(2));
+        value
+    }
+
+    fn condition_holds(&self, mnemonic: Mnemonic) -> bool {
+        use Mnemonic::*;
+        let flag = |f| self.flags.contains(f);
+        let less = flag(Flags::Sign) != flag(Flags::Overflow);
+        match mnemonic {
+            Je | Loopz => flag(Flags::Zero),
+            Jnz | Loopnz => !flag(Flags::Zero),
+            Jl => less,
+            Jnl => !less,
+            Jle => less || flag(Flags::Zero),
+            Jg => !less && !flag(Flags::Zero),
+            Jb => flag(Flags::Carry),
+            Jnb => !flag(Flags::Carry),
+            Jbe => flag(Flags::Carry) || flag(Flags::Zero),
+            Ja => !flag(Flags::Carry) && !flag(Flags::Zero),
+            Jp => flag(Flags::Parity),
+            Jnp => !flag(Flags::Parity),
+            Jo => flag(Flags::Overflow),
+            Jno => !flag(Flags::Overflow),
+            Js => flag(Flags::Sign),
+            Jns => !flag(Flags::Sign),
+            Jcxz => self.get_register(Register::CX) == 0,
+            _ => true,
+        }
+    }
+
+    fn jump_relative(&mut self, i: &Inst, ip_before: u16) -> anyhow::Result<()> {
+        let Some(Operand::RelativeJump(data::RelativeJump { offset, .. })) = i.operands.0 else {
+            return Err(anyhow!("invalid operand for {i}"));
+        };
+        // The offset counts from the start of the jump, as NASM's `$` does
+        self.jump_absolute(ip_before, ip_before.wrapping_add(offset as u16));
+        Ok(())
+    }
+
+    /// Moves execution to `target`, an offset into the code segment.
+    fn jump_absolute(&mut self, ip_before: u16, target: u16) {
+        self.ip = target;
+        self.update_ip(ip_before, target);
+    }
+
+    /// Transfers control for a near `jmp` or `call`, whether the target is relative or held in a
+    /// register or memory.
+    fn jump_near(&mut self, i: &Inst, ip_before: u16) -> anyhow::Result<()> {
+        match &i.operands.0 {
+            Some(Operand::RelativeJump(_)) => self.jump_relative(i, ip_before),
+            Some(operand @ (Operand::Register(_) | Operand::SizedMemory(_))) => {
+                let target = self.read_operand(operand, true)?;
+                self.jump_absolute(ip_before, target);
+                Ok(())
+            }
+            _ => Err(anyhow!("haven't implemented: {i} => {i:?}")),
+        }
+    }
+
+    fn do_op(
+        &mut self,
+        a: u16,
+        b: u16,
+        carry_in: bool,
+        is_wide: bool,
+        op: fn(u16, u16, bool, bool) -> (u16, Flags),
+    ) -> u16 {
+        let (mask, _) = width_masks(is_wide);
+        let (res, flags) = op(a & mask, b & mask, carry_in, is_wide);
+        self.update_flags(res, flags, is_wide);
         res
     }
 
-    pub(crate) fn print_registers(&self, ip: u64) {
-        println!();
-        println!("Final registers:");
+    pub(crate) fn registers_report(&self) -> anyhow::Result<String> {
+        let ip = self.ip;
+        let mut out = String::new();
+        writeln!(out)?;
+        writeln!(out, "Final registers:")?;
         for r in all::<Register>().filter(|r| matches!(r.get_type(), RegType::Wide)) {
             let val = self.get_register(r);
             if val > 0 {
-                println!("      {}: {val:#06x} ({val})", r.as_str());
+                writeln!(out, "      {}: {val:#06x} ({val})", r.as_str())?;
             }
         }
         if self.print_ip {
-            println!("      ip: {ip:#06x} ({ip})");
+            writeln!(out, "      ip: {ip:#06x} ({ip})")?;
         }
         if !self.flags.is_empty() {
-            println!("   flags: {}", self.flags);
+            writeln!(out, "   flags: {}", self.flags)?;
+        }
+        if self.format_version < 2 {
+            writeln!(out)?;
+            return Ok(out);
+        }
+        if self.dump_stack {
+            writeln!(out)?;
+            writeln!(out, "Stack:")?;
+            let sp = self.get_register(Register::SP);
+            let depth = self.stack_top.wrapping_sub(sp) / 2;
+            // SP above the top means more was popped than pushed; there's nothing to show
+            if depth < 0x4000 {
+                for ix in 0..depth {
+                    let address = sp.wrapping_add(ix * 2);
+                    let val = self
+                        .memory
+                        .read_word(self.physical_address(Register::SS, address));
+                    writeln!(out, "  {address:#06x}: {val:#06x} ({val})")?;
+                }
+            }
+        }
+        if !self.clock_totals.is_empty() {
+            writeln!(out)?;
+            writeln!(out, "Summary:")?;
+            writeln!(out, "  instructions: {}", self.instructions_executed)?;
+            for (cpu, total) in &self.clock_totals {
+                writeln!(out, "  {cpu} clocks: {total}")?;
+            }
+        }
+        if let Some(stats) = &self.mnemonic_stats {
+            writeln!(out)?;
+            writeln!(out, "Instruction counts:")?;
+            write!(out, "  {:<8} {:>8}", "mnemonic", "count")?;
+            for (cpu, _) in &self.clock_totals {
+                write!(out, " {:>12}", format!("{cpu} clocks"))?;
+            }
+            writeln!(out)?;
+            // Busiest first; ties stay in the order they first ran
+            let mut stats: Vec<_> = stats.iter().collect();
+            stats.sort_by_key(|s| std::cmp::Reverse(s.count));
+            for MnemonicStats {
+                mnemonic,
+                count,
+                clocks,
+            } in stats
+            {
+                write!(out, "  {:<8} {count:>8}", mnemonic.to_string())?;
+                for total in clocks {
+                    write!(out, " {total:>12}")?;
+                }
+                writeln!(out)?;
+            }
         }
-        println!()
+        if !self.console.is_empty() {
+            writeln!(out)?;
+            writeln!(out, "Console output:")?;
+            writeln!(out, "{}", String::from_utf8_lossy(&self.console).trim_end())?;
+        }
+        if let Some(code) = self.exit_code {
+            writeln!(out)?;
+            writeln!(out, "Exited with code {code}")?;
+        }
+        writeln!(out)?;
+        Ok(out)
     }
 
     fn update_register(&mut self, reg: Register, to_val: u16) {
         let from_val = self.registers[reg.get_reg_ix()];
         let to_val = match reg.get_type() {
-            RegType::Low => (from_val & 0b1111111100000000) + to_val,
+            RegType::Low => (from_val & 0b1111111100000000) + (to_val & 0b0000000011111111),
             RegType::High => (to_val << 8) + (from_val & 0b0000000011111111),
             RegType::Wide => to_val,
         };
         self.registers[reg.get_reg_ix()] = to_val;
-        self.last_update.reg_update = Some(RegUpdate {
-            reg,
-            from_val,
-            to_val,
-        })
+        for watched in &self.watched_registers {
+            if watched.get_reg_ix() != reg.get_reg_ix() {
+                continue;
+            }
+            let (from, to) = (watched.extract(from_val), watched.extract(to_val));
+            if from != to {
+                self.last_update.watch_hits.push((*watched, from, to));
+            }
+        }
+        let earlier = self
+            .last_update
+            .reg_updates
+            .iter_mut()
+            .find(|u| u.reg.get_reg_ix() == reg.get_reg_ix());
+        match earlier {
+            Some(earlier) => earlier.to_val = to_val,
+            None => self.last_update.reg_updates.push(RegUpdate {
+                reg,
+                from_val,
+                to_val,
+            }),
+        }
     }
 
-    fn update_flags(&mut self, result: u16, op_flags: Flags) {
-        let flags_before = self.flags;
-        self.flags = op_flags;
-        self.flags.set(Flags::Sign, (result as i16) < 0);
-        self.flags.set(Flags::Zero, result == 0);
-        self.flags.set(
+    /// Applies the ASCII (`aaa`/`aas`) and packed BCD (`daa`/`das`) adjustments to AL, driven by
+    /// the auxiliary carry left behind by the preceding add or subtract.
+    fn decimal_adjust(&mut self, mnemonic: Mnemonic) {
+        use crate::Register::{AH, AL, AX};
+        use Mnemonic::*;
+
+        let al = self.get_register(AL) as u8;
+        let low_nibble_overflow = al & 0xF > 9 || self.flags.contains(Flags::AuxCarry);
+        let mut flags = self.flags;
+        match mnemonic {
+            Aaa | Aas => {
+                let (al, ah) = match (low_nibble_overflow, mnemonic) {
+                    (false, _) => (al, self.get_register(AH) as u8),
+                    (true, Aaa) => (
+                        al.wrapping_add(6),
+                        (self.get_register(AH) as u8).wrapping_add(1),
+                    ),
+                    (true, _) => (
+                        al.wrapping_sub(6),
+                        (self.get_register(AH) as u8).wrapping_sub(1),
+                    ),
+                };
+                self.update_register(AX, create_word(al & 0xF, ah));
+                flags.set(Flags::AuxCarry | Flags::Carry, low_nibble_overflow);
+                self.set_flags(flags);
+            }
+            Daa | Das => {
+                let high_overflow = al > 0x99 || self.flags.contains(Flags::Carry);
+                let add = matches!(mnemonic, Daa);
+                let adjust = |value: u8, by: u8| {
+                    if add {
+                        value.wrapping_add(by)
+                    } else {
+                        value.wrapping_sub(by)
+                    }
+                };
+                let mut result = al;
+                if low_nibble_overflow {
+                    result = adjust(result, 0x06);
+                }
+                if high_overflow {
+                    result = adjust(result, 0x60);
+                }
+                self.update_register(AL, result.into());
+                flags.set(Flags::AuxCarry, low_nibble_overflow);
+                flags.set(Flags::Carry, high_overflow);
+                flags.set(Flags::Sign, result & 0x80 != 0);
+                flags.set(Flags::Zero, result == 0);
+                flags.set(Flags::Parity, result.count_ones().is_multiple_of(2));
+                self.set_flags(flags);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn update_flags(&mut self, result: u16, op_flags: Flags, is_wide: bool) {
+        let (mask, sign) = width_masks(is_wide);
+        // Arithmetic only ever produces status flags; the control flags carry over.
+        let mut flags = op_flags.union(self.flags.difference(Flags::ARITHMETIC));
+        flags.set(Flags::Sign, result & sign != 0);
+        flags.set(Flags::Zero, result & mask == 0);
+        flags.set(
             Flags::Parity,
-            (16 - (result & 0x00FF).count_zeros()) % 2 == 0,
+            (16 - (result & 0x00FF).count_zeros()).is_multiple_of(2),
         );
+        self.set_flags(flags);
+    }
+
+    fn set_flags(&mut self, flags: Flags) {
+        let flags_before = self.flags;
+        self.flags = flags;
 
         if flags_before.bits() != self.flags.bits() {
             self.last_update.flag_update = Some((flags_before, self.flags));
         }
     }
 
-    fn get_register(&self, reg: Register) -> u16 {
-        let val = self.registers[reg.get_reg_ix()];
-        match reg.get_type() {
-            RegType::Low => val & 0b0000000011111111,
-            RegType::High => (val & 0b1111111100000000) >> 8,
-            RegType::Wide => val,
+    /// Reads memory on the program's behalf, noting the access for any watchpoint it hits.
+    fn load(&mut self, address: u32, is_wide: bool) -> u16 {
+        let value = self.memory.read(address, is_wide);
+        self.record_access(address, is_wide, None, value);
+        value
+    }
+
+    /// Writes memory on the program's behalf, noting the access for any watchpoint it hits.
+    fn store(&mut self, address: u32, is_wide: bool, value: u16) {
+        let old = self.memory.read(address, is_wide);
+        self.memory.write(address, is_wide, value);
+        if let Some(entry) = self.journal.as_mut().and_then(|journal| journal.last_mut()) {
+            entry.writes.push((address, is_wide, old));
+        }
+        self.record_access(address, is_wide, Some(old), value);
+    }
+
+    fn record_access(&mut self, address: u32, is_wide: bool, old: Option<u16>, value: u16) {
+        let access = MemoryAccess {
+            ip: self.instruction_ip,
+            address,
+            is_wide,
+            old,
+            value,
+        };
+        if self
+            .watched_memory
+            .iter()
+            .any(|range| range.overlaps(address, is_wide))
+        {
+            self.last_update.memory_hits.push(access);
         }
+        if self.log_accesses {
+            self.last_update.accesses.push(access);
+        }
+    }
+
+    pub(crate) fn get_register(&self, reg: Register) -> u16 {
+        reg.extract(self.registers[reg.get_reg_ix()])
     }
 
-    fn update_ip(&mut self, ip_before: u64, ip_after: u64) {
+    /// Loads a register, memory or immediate operand, truncated to `is_wide`.
+    fn read_operand(&mut self, operand: &Operand, is_wide: bool) -> anyhow::Result<u16> {
+        let (mask, _) = width_masks(is_wide);
+        Ok(mask
+            & match operand {
+                Operand::Register(r) => self.get_register(*r),
+                Operand::MemoryAddress(m) => self.load(self.effective_address(m)?, is_wide),
+                Operand::SizedMemory(m) => self.load(self.effective_address(&m.address)?, is_wide),
+                Operand::DataArg(d) => (&d.data).into(),
+                Operand::Data(d) => d.into(),
+                _ => return Err(anyhow!("can't read from {operand}")),
+            })
+    }
+
+    /// Stores to a register or memory operand.
+    fn write_operand(
+        &mut self,
+        operand: &Operand,
+        is_wide: bool,
+        value: u16,
+    ) -> anyhow::Result<()> {
+        match operand {
+            Operand::Register(r) => self.update_register(*r, value),
+            Operand::MemoryAddress(m) => {
+                let address = self.effective_address(m)?;
+                self.store(address, is_wide, value)
+            }
+            Operand::SizedMemory(m) => {
+                let address = self.effective_address(&m.address)?;
+                self.store(address, is_wide, value)
+            }
+            _ => return Err(anyhow!("can't write to {operand}")),
+        }
+        Ok(())
+    }
+
+    /// The physical address of a memory operand: its offset within the override segment if the
+    /// instruction has one, otherwise within SS for BP-based addressing and DS for everything else.
+    fn effective_address(&self, address: &MemoryAddress) -> anyhow::Result<u32> {
+        use MemoryAddress::*;
+        let default_segment = match address {
+            Reg(Register::BP)
+            | RegnReg(Register::BP, _)
+            | RegnData(Register::BP, _)
+            | RegnRegnData(Register::BP, ..) => Register::SS,
+            _ => Register::DS,
+        };
+        let segment = self.segment_override.unwrap_or(default_segment);
+        let offset = match address {
+            Direct(d) => d.into(),
+            Reg(r) => self.get_register(*r),
+            RegnReg(r1, r2) => self.get_register(*r1).wrapping_add(self.get_register(*r2)),
+            RegnData(r, d) => self.get_register(*r).wrapping_add(d.into()),
+            RegnRegnData(r1, r2, d) => self
+                .get_register(*r1)
+                .wrapping_add(self.get_register(*r2))
+                .wrapping_add(d.into()),
+        };
+        Ok(self.physical_address(segment, offset))
+    }
+
+    pub(crate) fn physical_address(&self, segment: Register, offset: u16) -> u32 {
+        ((self.get_register(segment) as u32) << 4) + offset as u32
+    }
+
+    fn update_ip(&mut self, ip_before: u16, ip_after: u16) {
         self.last_update.ip_update = Some((ip_before, ip_after));
     }
 }
+
+/// Adds with carry-in at the given width, returning the result and the CF/AF/OF it produces.
+fn add(a: u16, b: u16, carry: bool, is_wide: bool) -> (u16, Flags) {
+    let (mask, sign) = width_masks(is_wide);
+    let sum = a as u32 + b as u32 + carry as u32;
+    let res = sum as u16 & mask;
+    let mut flags = Flags::empty();
+    flags.set(Flags::Carry, sum > mask as u32);
+    flags.set(Flags::AuxCarry, (a & 0xF) + (b & 0xF) + carry as u16 > 0xF);
+    flags.set(Flags::Overflow, (a ^ res) & (b ^ res) & sign != 0);
+    (res, flags)
+}
+
+/// Subtracts with borrow-in at the given width, returning the result and the CF/AF/OF it
+/// produces.
+fn subtract(a: u16, b: u16, borrow: bool, is_wide: bool) -> (u16, Flags) {
+    let (mask, sign) = width_masks(is_wide);
+    let c = borrow as u16;
+    let res = a.wrapping_sub(b).wrapping_sub(c) & mask;
+    let mut flags = Flags::empty();
+    flags.set(Flags::Carry, (a as u32) < b as u32 + c as u32);
+    flags.set(Flags::AuxCarry, (a & 0xF) < (b & 0xF) + c);
+    flags.set(Flags::Overflow, (a ^ b) & (a ^ res) & sign != 0);
+    (res, flags)
+}
+
+/// The destination and source of a two-operand instruction along with the width it operates on.
+fn binary_operands(i: &Inst) -> anyhow::Result<(&Operand, &Operand, bool)> {
+    let (Some(dest), Some(source)) = &i.operands else {
+        return Err(anyhow!("invalid operands for {i}"));
+    };
+    let is_wide = operand_width(dest)
+        .or_else(|| operand_width(source))
+        .ok_or_else(|| anyhow!("can't tell operand width of {i}"))?;
+    Ok((dest, source, is_wide))
+}
+
+/// The width an operand pins down on its own, if any. Bare memory addresses take their width
+/// from the other operand.
+fn operand_width(operand: &Operand) -> Option<bool> {
+    match operand {
+        Operand::Register(r) => Some(r.is_wide()),
+        Operand::SizedMemory(m) => Some(m.is_wide),
+        Operand::DataArg(d) if d.explicit => Some(d.data.is_wide()),
+        _ => None,
+    }
+}
+
+/// The value mask and sign bit for an operand of the given width.
+fn width_masks(is_wide: bool) -> (u16, u16) {
+    if is_wide {
+        (0xFFFF, 0x8000)
+    } else {
+        (0x00FF, 0x0080)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::RefCell, rc::Rc};
+
+    const SEGMENT: u16 = 0x1000;
+
+    fn computer(program: &[u8]) -> Computer {
+        Computer::new(
+            program,
+            FarPointer {
+                segment: SEGMENT,
+                offset: 0,
+            },
+            false,
+        )
+    }
+
+    /// Runs `computer` until it halts at the end of its program.
+    fn run(mut computer: Computer) -> Computer {
+        for _ in 0..1000 {
+            if let ExeResult::Halt = computer.execute_instruction().unwrap() {
+                return computer;
+            }
+        }
+        panic!("program didn't halt");
+    }
+
+    fn data_address(offset: u16) -> u32 {
+        u32::from(SEGMENT) * 16 + u32::from(offset)
+    }
+
+    #[test]
+    fn mov_reads_and_writes_memory_through_effective_addresses() {
+        // mov bx, 0x100; mov si, 0x10; mov ax, 0x1234; mov [bx + si + 2], ax;
+        // mov cx, [bx + si + 2]; mov [0x200], ah; mov dl, [0x200]
+        let c = run(computer(&[
+            0xBB, 0x00, 0x01, 0xBE, 0x10, 0x00, 0xB8, 0x34, 0x12, 0x89, 0x40, 0x02, 0x8B, 0x48,
+            0x02, 0x88, 0x26, 0x00, 0x02, 0x8A, 0x16, 0x00, 0x02,
+        ]));
+        assert_eq!(c.memory().read_word(data_address(0x112)), 0x1234);
+        assert_eq!(c.get_register(Register::CX), 0x1234);
+        assert_eq!(c.memory().read_byte(data_address(0x200)), 0x12);
+        assert_eq!(c.memory().read_byte(data_address(0x201)), 0);
+        assert_eq!(c.get_register(Register::DL), 0x12);
+    }
+
+    #[test]
+    fn shl_sets_carry_and_overflow_from_the_bit_shifted_out() {
+        // mov al, 0x81; shl al, 1
+        let c = run(computer(&[0xB0, 0x81, 0xD0, 0xE0]));
+        assert_eq!(c.get_register(Register::AL), 0x02);
+        assert!(c.flags().contains(Flags::Carry | Flags::Overflow));
+    }
+
+    #[test]
+    fn sar_by_cl_keeps_the_sign() {
+        // mov al, 0x80; mov cl, 3; sar al, cl
+        let c = run(computer(&[0xB0, 0x80, 0xB1, 0x03, 0xD2, 0xF8]));
+        assert_eq!(c.get_register(Register::AL), 0xF0);
+        assert!(!c.flags().contains(Flags::Carry));
+        assert!(c.flags().contains(Flags::Sign));
+    }
+
+    #[test]
+    fn rcr_rotates_through_carry() {
+        // stc; mov al, 1; rcr al, 1
+        let c = run(computer(&[0xF9, 0xB0, 0x01, 0xD0, 0xD8]));
+        assert_eq!(c.get_register(Register::AL), 0x80);
+        assert!(c.flags().contains(Flags::Carry));
+    }
+
+    #[test]
+    fn mul_spills_into_dx_and_sets_carry() {
+        // mov ax, 0x1234; mov bx, 0x100; mul bx
+        let c = run(computer(&[0xB8, 0x34, 0x12, 0xBB, 0x00, 0x01, 0xF7, 0xE3]));
+        assert_eq!(c.get_register(Register::DX), 0x0012);
+        assert_eq!(c.get_register(Register::AX), 0x3400);
+        assert!(c.flags().contains(Flags::Carry | Flags::Overflow));
+    }
+
+    #[test]
+    fn imul_byte_sign_extends_into_ah() {
+        // mov al, -2; mov bl, 3; imul bl
+        let c = run(computer(&[0xB0, 0xFE, 0xB3, 0x03, 0xF6, 0xEB]));
+        assert_eq!(c.get_register(Register::AX), 0xFFFA);
+        assert!(!c.flags().contains(Flags::Carry));
+    }
+
+    #[test]
+    fn div_and_idiv_leave_quotient_and_remainder() {
+        // mov dx, 0; mov ax, 100; mov cx, 7; div cx
+        let c = run(computer(&[
+            0xBA, 0x00, 0x00, 0xB8, 0x64, 0x00, 0xB9, 0x07, 0x00, 0xF7, 0xF1,
+        ]));
+        assert_eq!(c.get_register(Register::AX), 14);
+        assert_eq!(c.get_register(Register::DX), 2);
+
+        // mov ax, -7; mov bl, 2; idiv bl
+        let c = run(computer(&[0xB8, 0xF9, 0xFF, 0xB3, 0x02, 0xF6, 0xFB]));
+        assert_eq!(c.get_register(Register::AL), 0xFD);
+        assert_eq!(c.get_register(Register::AH), 0xFF);
+    }
+
+    #[test]
+    fn divide_by_zero_without_a_handler_is_an_error() {
+        // mov bl, 0; div bl
+        let mut c = computer(&[0xB3, 0x00, 0xF6, 0xF3]);
+        c.execute_instruction().unwrap();
+        assert!(c.execute_instruction().is_err());
+    }
+
+    #[test]
+    fn rep_movsb_copies_cx_bytes() {
+        // mov si, 0x200; mov di, 0x300; mov cx, 4; cld; rep movsb
+        let mut c = computer(&[
+            0xBE, 0x00, 0x02, 0xBF, 0x00, 0x03, 0xB9, 0x04, 0x00, 0xFC, 0xF3, 0xA4,
+        ]);
+        for (ix, byte) in [1, 2, 3, 4].into_iter().enumerate() {
+            c.memory_mut()
+                .write_byte(data_address(0x200) + ix as u32, byte);
+        }
+        let c = run(c);
+        let copied: Vec<_> = (0..4)
+            .map(|ix| c.memory().read_byte(data_address(0x300) + ix))
+            .collect();
+        assert_eq!(copied, [1, 2, 3, 4]);
+        assert_eq!(c.get_register(Register::CX), 0);
+        assert_eq!(c.get_register(Register::SI), 0x204);
+    }
+
+    #[test]
+    fn repne_scasb_stops_on_a_match() {
+        // mov di, 0x200; mov cx, 8; mov al, 0x33; repne scasb
+        let mut c = computer(&[0xBF, 0x00, 0x02, 0xB9, 0x08, 0x00, 0xB0, 0x33, 0xF2, 0xAE]);
+        for (ix, byte) in [0x11, 0x22, 0x33, 0x44].into_iter().enumerate() {
+            c.memory_mut()
+                .write_byte(data_address(0x200) + ix as u32, byte);
+        }
+        let c = run(c);
+        assert_eq!(c.get_register(Register::DI), 0x203);
+        assert_eq!(c.get_register(Register::CX), 5);
+        assert!(c.flags().contains(Flags::Zero));
+    }
+
+    #[test]
+    fn rep_stosw_fills_words() {
+        // mov ax, 0xabcd; mov di, 0x300; mov cx, 2; rep stosw
+        let c = run(computer(&[
+            0xB8, 0xCD, 0xAB, 0xBF, 0x00, 0x03, 0xB9, 0x02, 0x00, 0xF3, 0xAB,
+        ]));
+        assert_eq!(c.memory().read_word(data_address(0x300)), 0xABCD);
+        assert_eq!(c.memory().read_word(data_address(0x302)), 0xABCD);
+        assert_eq!(c.get_register(Register::DI), 0x304);
+    }
+
+    #[test]
+    fn daa_adjusts_packed_bcd() {
+        // mov al, 0x19; add al, 0x28; daa
+        let c = run(computer(&[0xB0, 0x19, 0x04, 0x28, 0x27]));
+        assert_eq!(c.get_register(Register::AL), 0x47);
+
+        // mov al, 0x99; add al, 1; daa
+        let c = run(computer(&[0xB0, 0x99, 0x04, 0x01, 0x27]));
+        assert_eq!(c.get_register(Register::AL), 0x00);
+        assert!(c.flags().contains(Flags::Carry | Flags::Zero));
+    }
+
+    #[test]
+    fn aaa_carries_into_ah() {
+        // mov ax, 9; add al, 3; aaa
+        let c = run(computer(&[0xB8, 0x09, 0x00, 0x04, 0x03, 0x37]));
+        assert_eq!(c.get_register(Register::AX), 0x0102);
+        assert!(c.flags().contains(Flags::Carry | Flags::AuxCarry));
+    }
+
+    /// A port and the byte written to it, or `None` for a read.
+    type PortAccess = (u16, Option<u8>);
+
+    /// Records every port access, answering reads with the port's low byte.
+    #[derive(Debug, Default)]
+    struct RecordingBus(Rc<RefCell<Vec<PortAccess>>>);
+
+    impl IoBus for RecordingBus {
+        fn port_in(&mut self, port: u16) -> u8 {
+            self.0.borrow_mut().push((port, None));
+            port as u8
+        }
+
+        fn port_out(&mut self, port: u16, value: u8) {
+            self.0.borrow_mut().push((port, Some(value)));
+        }
+    }
+
+    #[test]
+    fn word_io_is_two_byte_accesses() {
+        let bus = RecordingBus::default();
+        let accesses = bus.0.clone();
+        // in ax, 0x60; mov ax, 0x1234; out 0x70, ax
+        let c = run(computer(&[0xE5, 0x60, 0xB8, 0x34, 0x12, 0xE7, 0x70]).with_io_bus(bus));
+        assert_eq!(
+            *accesses.borrow(),
+            [
+                (0x60, None),
+                (0x61, None),
+                (0x70, Some(0x34)),
+                (0x71, Some(0x12))
+            ]
+        );
+        assert_eq!(c.get_register(Register::AX), 0x1234);
+
+        let bus = RecordingBus::default();
+        let accesses = bus.0.clone();
+        // in ax, 0x60
+        let c = run(computer(&[0xE5, 0x60]).with_io_bus(bus));
+        assert_eq!(c.get_register(Register::AX), 0x6160);
+        assert_eq!(accesses.borrow().len(), 2);
+    }
+}
@@ -0,0 +1,57 @@
+use crate::iobus::Device;
+use std::io::{self, Read, Write};
+
+const INPUT_READY: u8 = 1 << 0;
+const OUTPUT_READY: u8 = 1 << 1;
+
+/// A terminal on two ports: the data port is stdin and stdout, the status port after it says
+/// whether there's more to read.
+#[derive(Debug)]
+pub(crate) struct ConsoleDevice {
+    data_port: u16,
+    at_eof: bool,
+}
+
+impl ConsoleDevice {
+    pub(crate) fn new(data_port: u16) -> Self {
+        Self {
+            data_port,
+            at_eof: false,
+        }
+    }
+
+    fn status_port(&self) -> u16 {
+        self.data_port.wrapping_add(1)
+    }
+}
+
+impl Device for ConsoleDevice {
+    fn claims(&self, port: u16) -> bool {
+        port == self.data_port || port == self.status_port()
+    }
+
+    fn port_in(&mut self, port: u16) -> u8 {
+        if port == self.data_port {
+            let mut byte = [0];
+            match io::stdin().read(&mut byte) {
+                Ok(1) => return byte[0],
+                // End of input reads as zero from then on
+                _ => self.at_eof = true,
+            }
+            0
+        } else {
+            OUTPUT_READY | if self.at_eof { 0 } else { INPUT_READY }
+        }
+    }
+
+    fn port_out(&mut self, port: u16, value: u8) {
+        if port == self.data_port {
+            let mut stdout = io::stdout();
+            let _ = stdout.write_all(&[value]).and_then(|_| stdout.flush());
+        }
+    }
+
+    fn reset(&mut self) {
+        self.at_eof = false;
+    }
+}
@@ -28,6 +28,10 @@ impl Data {
             (false, _) => Data::Byte(bytes.next()?),
         })
     }
+    pub(crate) fn is_wide(&self) -> bool {
+        matches!(self, Data::Word(_))
+    }
+
     pub(crate) fn to_word(b1: u8, b2: u8) -> Self {
         Data::Word(create_word(b1, b2))
     }
@@ -86,10 +90,23 @@ pub(crate) enum Displacement {
     Word(u16),
 }
 
+impl From<&Displacement> for u16 {
+    fn from(val: &Displacement) -> Self {
+        match val {
+            Displacement::Byte(b) => *b as i8 as u16,
+            Displacement::Word(w) => *w,
+        }
+    }
+}
+
 impl Displacement {
     pub(crate) fn to_word(b1: u8, b2: u8) -> Self {
         Displacement::Word(create_word(b1, b2))
     }
+
+    pub(crate) fn is_zero(&self) -> bool {
+        u16::from(self) == 0
+    }
 }
 
 impl Display for Displacement {
@@ -114,15 +131,39 @@ pub(crate) fn create_word(b1: u8, b2: u8) -> u16 {
 
 #[derive(Debug)]
 pub(crate) struct RelativeJump {
-    pub(crate) offset: i8,
+    pub(crate) offset: i16,
+    /// Whether the jump was encoded with a 16-bit displacement that the assembler might
+    /// otherwise shrink to 8 bits.
+    pub(crate) near: bool,
 }
 
 impl Display for RelativeJump {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.near {
+            f.write_str("near ")?;
+        }
         if self.offset >= 0 {
             write!(f, "$+{}", self.offset)
         } else {
-            write!(f, "$-{}", -self.offset)
+            write!(f, "$-{}", self.offset.unsigned_abs())
+        }
+    }
+}
+
+/// A direct `segment:offset` operand, as used by intersegment calls and jumps. The alternate
+/// form writes both halves in hex, the way traces show register values.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FarPointer {
+    pub(crate) segment: u16,
+    pub(crate) offset: u16,
+}
+
+impl Display for FarPointer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            write!(f, "{:#06x}:{:#06x}", self.segment, self.offset)
+        } else {
+            write!(f, "{}:{}", self.segment, self.offset)
         }
     }
 }
@@ -0,0 +1,172 @@
+use crate::{
+    computer::{Computer, ExeResult},
+    memory::Memory,
+    overrides::parse_offset,
+};
+use anyhow::anyhow;
+use std::io::{self, Write};
+
+const HELP: &str = "\
+commands:
+  step [N]       run N instructions (default 1)
+  back [N]       undo the last N instructions (default 1)
+  continue       run until a breakpoint or the program halts
+  break [ADDR]   stop when IP reaches ADDR, or list the breakpoints
+  regs           show the registers and flags
+  x/N ADDR       show N bytes of memory from physical address ADDR (default 16)
+  disas [N]      disassemble N instructions from IP (default 5)
+  quit           leave the debugger";
+
+/// An interactive prompt for stepping through a program.
+pub(crate) struct Debugger<'a> {
+    computer: &'a mut Computer,
+    breakpoints: Vec<u16>,
+    print_ip: bool,
+    halted: bool,
+}
+
+impl<'a> Debugger<'a> {
+    pub(crate) fn new(computer: &'a mut Computer, breakpoints: Vec<u16>, print_ip: bool) -> Self {
+        Self {
+            computer,
+            breakpoints,
+            print_ip,
+            halted: false,
+        }
+    }
+
+    pub(crate) fn run(&mut self) -> anyhow::Result<()> {
+        println!("type `help` for a list of commands");
+        let mut line = String::new();
+        loop {
+            print!("(8086) ");
+            io::stdout().flush()?;
+            line.clear();
+            if io::stdin().read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+            match self.command(&line) {
+                Ok(true) => {}
+                Ok(false) => return Ok(()),
+                Err(e) => println!("error: {e:#}"),
+            }
+        }
+    }
+
+    fn command(&mut self, line: &str) -> anyhow::Result<bool> {
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            return Ok(true);
+        };
+        match command {
+            "s" | "step" => {
+                for _ in 0..count(words.next(), 1)? {
+                    if !self.step()? {
+                        break;
+                    }
+                }
+            }
+            "bs" | "back" => {
+                for _ in 0..count(words.next(), 1)? {
+                    if !self.computer.step_back() {
+                        println!("at the start of the recording");
+                        break;
+                    }
+                    self.halted = false;
+                }
+                let ip = self.computer.ip();
+                let (i, _) = self.computer.decode_at(ip)?;
+                println!("back at ip {ip:#x}: {i}");
+            }
+            "c" | "continue" => {
+                while self.step()? {
+                    let ip = self.computer.ip();
+                    if self.breakpoints.contains(&ip) {
+                        println!("break at ip {ip:#x}");
+                        break;
+                    }
+                }
+            }
+            "b" | "break" => match words.next() {
+                Some(address) => {
+                    let ip = parse_offset(address)
+                        .and_then(|ip| u16::try_from(ip).ok())
+                        .ok_or_else(|| anyhow!("invalid address: {address}"))?;
+                    if !self.breakpoints.contains(&ip) {
+                        self.breakpoints.push(ip);
+                    }
+                }
+                None => {
+                    for ip in &self.breakpoints {
+                        println!("  {ip:#06x}");
+                    }
+                }
+            },
+            "r" | "regs" => println!("{}", self.computer.snapshot()),
+            "d" | "disas" => {
+                let mut ip = self.computer.ip();
+                for _ in 0..count(words.next(), 5)? {
+                    let (i, len) = self.computer.decode_at(ip)?;
+                    println!("  {ip:#06x}: {i}");
+                    ip = ip.wrapping_add(len);
+                }
+            }
+            "q" | "quit" => return Ok(false),
+            "h" | "help" => println!("{HELP}"),
+            _ if command == "x" || command.starts_with("x/") => {
+                let bytes = count(command.strip_prefix("x/"), 16)?;
+                let address = words.next().ok_or_else(|| anyhow!("x needs an address"))?;
+                let start =
+                    parse_offset(address).ok_or_else(|| anyhow!("invalid address: {address}"))?;
+                let end = start
+                    .checked_add(bytes)
+                    .filter(|&end| end <= Memory::SIZE)
+                    .ok_or_else(|| anyhow!("{address} plus {bytes} bytes runs past 1 MiB"))?;
+                self.examine(start as u32, end as u32);
+            }
+            _ => return Err(anyhow!("unknown command `{command}`; try `help`")),
+        }
+        Ok(true)
+    }
+
+    fn step(&mut self) -> anyhow::Result<bool> {
+        if self.halted {
+            println!("the program has halted");
+            return Ok(false);
+        }
+        match self.computer.execute_instruction()? {
+            ExeResult::Success(i, update) => {
+                println!("{i} ; {} ", update.print(self.print_ip)?);
+                if let Some(warning) = update.flag_warning() {
+                    println!("  warning: {warning}");
+                }
+                for hit in update.watch_hits() {
+                    println!("  {hit}");
+                }
+                Ok(true)
+            }
+            ExeResult::Halt => {
+                self.halted = true;
+                print!("{}", self.computer.registers_report()?);
+                Ok(false)
+            }
+        }
+    }
+
+    fn examine(&self, start: u32, end: u32) {
+        let memory = self.computer.memory();
+        for row in (start..end).step_by(16) {
+            let hex: Vec<_> = (row..(row + 16).min(end))
+                .map(|address| format!("{:02x}", memory.read_byte(address)))
+                .collect();
+            println!("  {row:#07x}: {}", hex.join(" "));
+        }
+    }
+}
+
+fn count(word: Option<&str>, default: usize) -> anyhow::Result<usize> {
+    match word {
+        Some(word) => parse_offset(word).ok_or_else(|| anyhow!("invalid count: {word}")),
+        None => Ok(default),
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{
+    bytestream::ByteStream,
+    instruction::{Inst, Operand},
+};
+use std::{
+    collections::HashSet,
+    io::{BufReader, Cursor},
+};
+
+/// Only offsets this close to the start of an image are considered as entry points.
+const MAX_CANDIDATES: usize = 256;
+const PROLOGUE_BONUS: i64 = 16;
+const CLEAN_END_BONUS: i64 = 8;
+const REFERENCED_PENALTY: i64 = 8;
+
+#[derive(Debug)]
+pub(crate) struct Candidate {
+    pub(crate) offset: usize,
+    pub(crate) score: i64,
+    pub(crate) instructions: usize,
+    pub(crate) prologue: bool,
+    pub(crate) referenced: bool,
+}
+
+#[derive(Debug)]
+struct Run {
+    bytes: usize,
+    instructions: usize,
+    clean_end: bool,
+    jump_targets: Vec<usize>,
+}
+
+/// The most plausible entry point among an image's early offsets, preferring lower ones.
+pub(crate) fn detect_entry_point(image: &[u8]) -> Option<Candidate> {
+    let runs: Vec<_> = (0..image.len().min(MAX_CANDIDATES))
+        .map(|offset| (offset, decode_run(image, offset)))
+        .collect();
+    let referenced: HashSet<_> = runs
+        .iter()
+        .flat_map(|(_, run)| run.jump_targets.iter().copied())
+        .collect();
+
+    runs.into_iter()
+        .map(|(offset, run)| {
+            let prologue = has_prologue(&image[offset..]);
+            let referenced = referenced.contains(&offset);
+            let mut score = run.bytes as i64;
+            if prologue {
+                score += PROLOGUE_BONUS;
+            }
+            if run.clean_end {
+                score += CLEAN_END_BONUS;
+            }
+            if referenced {
+                score -= REFERENCED_PENALTY;
+            }
+            Candidate {
+                offset,
+                score,
+                instructions: run.instructions,
+                prologue,
+                referenced,
+            }
+        })
+        .reduce(|best, c| if c.score > best.score { c } else { best })
+}
+
+fn decode_run(image: &[u8], offset: usize) -> Run {
+    let mut bytes = ByteStream {
+        reader: BufReader::new(Cursor::new(&image[offset..])),
+    };
+    let mut run = Run {
+        bytes: 0,
+        instructions: 0,
+        clean_end: false,
+        jump_targets: vec![],
+    };
+    loop {
+        match Inst::parse(&mut bytes) {
+            Ok(Some(inst)) => {
+                if let Some(Operand::RelativeJump(jump)) = &inst.operands.0 {
+                    let target = (offset + run.bytes) as i64 + jump.offset as i64;
+                    if let Ok(target) = usize::try_from(target) {
+                        run.jump_targets.push(target);
+                    }
+                }
+                run.instructions += 1;
+                run.bytes = bytes.get_iptr().unwrap_or_default() as usize;
+            }
+            Ok(None) => {
+                run.clean_end = true;
+                return run;
+            }
+            Err(_) => return run,
+        }
+    }
+}
+
+fn has_prologue(bytes: &[u8]) -> bool {
+    // push bp; mov bp, sp (either direction encoding)
+    matches!(bytes, [0x55, 0x89, 0xe5, ..] | [0x55, 0x8b, 0xec, ..])
+}
@@ -0,0 +1,81 @@
+use crate::{computer::Flags, instruction::Mnemonic};
+
+/// Warns about conditional jumps reading flags never set or left undefined, as `jz` after `mul`.
+#[derive(Debug, Default)]
+pub(crate) struct FlagChecker {
+    last_writers: Vec<(Flags, Mnemonic, u64, bool)>,
+}
+
+impl FlagChecker {
+    pub(crate) fn check(&mut self, mnemonic: Mnemonic, ip: u64) -> Option<String> {
+        let warning = flags_read(mnemonic).iter().find_map(|flag| {
+            match self.last_writers.iter().find(|(f, ..)| *f == flag) {
+                None => Some(format!(
+                    "{mnemonic} at {ip:#x} reads flag {flag}, but no instruction has set it"
+                )),
+                Some((_, writer, writer_ip, false)) => Some(format!(
+                    "{mnemonic} at {ip:#x} reads flag {flag}, which the {writer} at {writer_ip:#x} left undefined"
+                )),
+                Some(_) => None,
+            }
+        });
+
+        let undefined = flags_undefined(mnemonic);
+        for flag in flags_written(mnemonic).union(undefined).iter() {
+            self.last_writers.retain(|(f, ..)| *f != flag);
+            self.last_writers
+                .push((flag, mnemonic, ip, !undefined.contains(flag)));
+        }
+
+        warning
+    }
+}
+
+fn flags_read(mnemonic: Mnemonic) -> Flags {
+    use Mnemonic::*;
+    match mnemonic {
+        Je | Jnz | Loopz | Loopnz => Flags::Zero,
+        Jb | Jnb => Flags::Carry,
+        Jbe | Ja => Flags::Carry | Flags::Zero,
+        Jl | Jnl => Flags::Sign | Flags::Overflow,
+        Jle | Jg => Flags::Sign | Flags::Overflow | Flags::Zero,
+        Jp | Jnp => Flags::Parity,
+        Jo | Jno | Into => Flags::Overflow,
+        Js | Jns => Flags::Sign,
+        _ => Flags::empty(),
+    }
+}
+
+fn flags_written(mnemonic: Mnemonic) -> Flags {
+    use Mnemonic::*;
+    match mnemonic {
+        Add | Adc | Sub | Sbb | Cmp | Neg | Cmpsb | Cmpsw | Scasb | Scasw => Flags::ARITHMETIC,
+        Popf | Iret => Flags::all(),
+        Sahf => Flags::ARITHMETIC.difference(Flags::Overflow),
+        Inc | Dec => Flags::ARITHMETIC.difference(Flags::Carry),
+        Clc | Cmc | Stc => Flags::Carry,
+        Cld | Std => Flags::Direction,
+        Cli | Sti => Flags::Interrupt,
+        Int | Int3 | Into => Flags::Interrupt | Flags::Trap,
+        Aaa | Aas => Flags::AuxCarry | Flags::Carry,
+        Aam | Aad => Flags::Sign | Flags::Zero | Flags::Parity,
+        Daa | Das => Flags::ARITHMETIC.difference(Flags::Overflow),
+        Mul | Imul | Rol | Ror | Rcl | Rcr => Flags::Carry | Flags::Overflow,
+        Shl | Shr | Sar | And | Test | Or | Xor => Flags::ARITHMETIC.difference(Flags::AuxCarry),
+        _ => Flags::empty(),
+    }
+}
+
+/// The flags an instruction changes to an unpredictable value.
+fn flags_undefined(mnemonic: Mnemonic) -> Flags {
+    use Mnemonic::*;
+    match mnemonic {
+        Aaa | Aas => Flags::Overflow | Flags::Sign | Flags::Zero | Flags::Parity,
+        Aam | Aad => Flags::Overflow | Flags::AuxCarry | Flags::Carry,
+        Daa | Das => Flags::Overflow,
+        Mul | Imul => Flags::Sign | Flags::Zero | Flags::AuxCarry | Flags::Parity,
+        Div | Idiv => Flags::ARITHMETIC,
+        Shl | Shr | Sar | And | Test | Or | Xor => Flags::AuxCarry,
+        _ => Flags::empty(),
+    }
+}
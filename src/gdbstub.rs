@@ -0,0 +1,295 @@
+use crate::{
+    computer::{Computer, ExeResult},
+    register::Register,
+};
+use anyhow::anyhow;
+use std::{
+    io::{BufRead, BufReader, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+};
+
+/// The general registers in the order GDB's i386 target numbers them, with 32-bit slots.
+const GENERAL: [Register; 8] = [
+    Register::AX,
+    Register::CX,
+    Register::DX,
+    Register::BX,
+    Register::SP,
+    Register::BP,
+    Register::SI,
+    Register::DI,
+];
+const IP: usize = 8;
+const FLAGS: usize = 9;
+/// Followed by FS and GS, which the 8086 doesn't have and always read as zero.
+const SEGMENTS: [Register; 4] = [Register::CS, Register::SS, Register::DS, Register::ES];
+const REGISTER_COUNT: usize = 16;
+const PACKET_SIZE: u32 = 0x1000;
+const POLL_INTERVAL: u64 = 0x400;
+const INTERRUPT: u8 = 0x03;
+
+/// Waits for GDB to connect on `port`, then lets it drive `computer`.
+pub(crate) fn serve(
+    computer: &mut Computer,
+    breakpoints: Vec<u16>,
+    port: u16,
+) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    eprintln!("waiting for gdb on port {port}: target remote localhost:{port}");
+    let (stream, peer) = listener.accept()?;
+    eprintln!("gdb connected from {peer}");
+    // `--break` addresses are offsets into the code segment; GDB's are physical
+    let breakpoints = breakpoints
+        .into_iter()
+        .map(|ip| computer.physical_address(Register::CS, ip))
+        .collect();
+    GdbStub {
+        computer,
+        reader: BufReader::new(stream.try_clone()?),
+        writer: stream,
+        breakpoints,
+        halted: false,
+    }
+    .run()
+}
+
+struct GdbStub<'a> {
+    computer: &'a mut Computer,
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    breakpoints: Vec<u32>,
+    halted: bool,
+}
+
+impl GdbStub<'_> {
+    fn run(&mut self) -> anyhow::Result<()> {
+        while let Some(packet) = self.read_packet()? {
+            let reply = match packet.as_bytes().first() {
+                Some(b'k') => return Ok(()),
+                Some(b'D') => {
+                    self.send("OK")?;
+                    return Ok(());
+                }
+                _ => self.handle(&packet).unwrap_or_else(|e| {
+                    eprintln!("gdb: {packet}: {e:#}");
+                    "E01".to_string()
+                }),
+            };
+            self.send(&reply)?;
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, packet: &str) -> anyhow::Result<String> {
+        let (command, args) = packet
+            .split_at_checked(1)
+            .ok_or_else(|| anyhow!("malformed packet"))?;
+        Ok(match command {
+            "?" => self.stop_reply(),
+            "g" => (0..REGISTER_COUNT)
+                .map(|n| hex_u32(self.read_register(n)))
+                .collect(),
+            "G" => {
+                for (n, chunk) in args.as_bytes().chunks(8).take(REGISTER_COUNT).enumerate() {
+                    self.write_register(n, parse_u32(std::str::from_utf8(chunk)?)?);
+                }
+                "OK".into()
+            }
+            "p" => hex_u32(self.read_register(parse_hex(args)? as usize)),
+            "P" => {
+                let (n, value) = args.split_once('=').ok_or(anyhow!("malformed P"))?;
+                self.write_register(parse_hex(n)? as usize, parse_u32(value)?);
+                "OK".into()
+            }
+            "m" => {
+                let (address, len) = address_len(args)?;
+                // Each byte comes back as two hex digits
+                if len > PACKET_SIZE / 2 {
+                    return Err(anyhow!("can't read {len:#x} bytes in one packet"));
+                }
+                let end = address
+                    .checked_add(len)
+                    .ok_or_else(|| anyhow!("memory range overflows"))?;
+                (address..end)
+                    .map(|a| format!("{:02x}", self.computer.memory().read_byte(a)))
+                    .collect()
+            }
+            "M" => {
+                let (region, data) = args.split_once(':').ok_or(anyhow!("malformed M"))?;
+                let (address, len) = address_len(region)?;
+                address
+                    .checked_add(len)
+                    .ok_or_else(|| anyhow!("memory range overflows"))?;
+                let bytes = data
+                    .as_bytes()
+                    .chunks(2)
+                    .take(len as usize)
+                    .map(|pair| Ok(u8::from_str_radix(std::str::from_utf8(pair)?, 16)?))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                for (ix, byte) in bytes.into_iter().enumerate() {
+                    self.computer
+                        .memory_mut()
+                        .write_byte(address + ix as u32, byte);
+                }
+                "OK".into()
+            }
+            "s" => match self.step() {
+                Ok(_) => self.stop_reply(),
+                Err(e) => fault(e),
+            },
+            "c" => {
+                for n in 1.. {
+                    match self.step() {
+                        Ok(true) if self.breakpoints.contains(&self.pc()) => break,
+                        Ok(true) if n % POLL_INTERVAL == 0 && self.interrupted()? => {
+                            return Ok("S02".into());
+                        }
+                        Ok(true) => {}
+                        Ok(false) => break,
+                        Err(e) => return Ok(fault(e)),
+                    }
+                }
+                self.stop_reply()
+            }
+            "Z" | "z" if args.starts_with('0') => {
+                let args = args
+                    .get(2..)
+                    .ok_or_else(|| anyhow!("malformed breakpoint"))?;
+                let (address, _) = address_len(args)?;
+                self.breakpoints.retain(|&b| b != address);
+                if command == "Z" {
+                    self.breakpoints.push(address);
+                }
+                "OK".into()
+            }
+            "H" => "OK".into(),
+            "q" if args.starts_with("Supported") => format!("PacketSize={PACKET_SIZE:x}"),
+            "q" if args == "Attached" => "1".into(),
+            "q" if args == "C" => "QC1".into(),
+            _ => String::new(),
+        })
+    }
+
+    fn step(&mut self) -> anyhow::Result<bool> {
+        if !self.halted {
+            self.halted = matches!(self.computer.execute_instruction()?, ExeResult::Halt);
+        }
+        Ok(!self.halted)
+    }
+
+    fn interrupted(&mut self) -> anyhow::Result<bool> {
+        if !self.reader.buffer().is_empty() {
+            let interrupt = self.reader.buffer()[0] == INTERRUPT;
+            if interrupt {
+                self.reader.consume(1);
+            }
+            return Ok(interrupt);
+        }
+        let mut byte = [0];
+        self.writer.set_nonblocking(true)?;
+        let peeked = self.writer.peek(&mut byte);
+        self.writer.set_nonblocking(false)?;
+        match peeked {
+            Ok(1) if byte[0] == INTERRUPT => {
+                self.reader.read_exact(&mut byte)?;
+                Ok(true)
+            }
+            // Hung up: stop, and the next read finds the connection closed
+            Ok(0) => Ok(true),
+            Ok(_) => Ok(false),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn stop_reply(&self) -> String {
+        if self.halted {
+            format!("W{:02x}", self.computer.exit_code().unwrap_or(0))
+        } else {
+            "S05".into()
+        }
+    }
+
+    fn pc(&self) -> u32 {
+        self.computer
+            .physical_address(Register::CS, self.computer.ip())
+    }
+
+    fn read_register(&self, n: usize) -> u32 {
+        match n {
+            0..8 => self.computer.get_register(GENERAL[n]).into(),
+            IP => self.computer.ip().into(),
+            FLAGS => self.computer.flags().to_word().into(),
+            10..14 => self.computer.get_register(SEGMENTS[n - 10]).into(),
+            _ => 0,
+        }
+    }
+
+    fn write_register(&mut self, n: usize, value: u32) {
+        let value = value as u16;
+        match n {
+            0..8 => self.computer.set_register(GENERAL[n], value),
+            IP => self.computer.set_ip(value),
+            FLAGS => self.computer.set_flags_word(value),
+            10..14 => self.computer.set_register(SEGMENTS[n - 10], value),
+            _ => {}
+        }
+    }
+
+    /// Reads the next `$packet#checksum`, acknowledging it. `None` once GDB hangs up.
+    fn read_packet(&mut self) -> anyhow::Result<Option<String>> {
+        let mut skipped = vec![];
+        if self.reader.read_until(b'$', &mut skipped)? == 0 || skipped.last() != Some(&b'$') {
+            return Ok(None);
+        }
+        let mut body = vec![];
+        self.reader.read_until(b'#', &mut body)?;
+        if body.pop() != Some(b'#') {
+            return Ok(None);
+        }
+        let mut checksum = [0; 2];
+        self.reader.read_exact(&mut checksum)?;
+        self.writer.write_all(b"+")?;
+        Ok(Some(String::from_utf8(body)?))
+    }
+
+    fn send(&mut self, reply: &str) -> anyhow::Result<()> {
+        let checksum = reply.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+        write!(self.writer, "${reply}#{checksum:02x}")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// SIGILL, for when the program hits something the emulator can't run.
+fn fault(e: anyhow::Error) -> String {
+    eprintln!("gdb: stopped: {e:#}");
+    "S04".into()
+}
+
+fn hex_u32(value: u32) -> String {
+    value
+        .to_le_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+fn parse_u32(hex: &str) -> anyhow::Result<u32> {
+    let mut bytes = [0; 4];
+    for (ix, pair) in hex.as_bytes().chunks(2).take(4).enumerate() {
+        bytes[ix] = u8::from_str_radix(std::str::from_utf8(pair)?, 16)?;
+    }
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn parse_hex(hex: &str) -> anyhow::Result<u32> {
+    Ok(u32::from_str_radix(hex, 16)?)
+}
+
+fn address_len(args: &str) -> anyhow::Result<(u32, u32)> {
+    let (address, len) = args
+        .split_once(',')
+        .ok_or_else(|| anyhow!("expected ADDR,LEN, got {args}"))?;
+    Ok((parse_hex(address)?, parse_hex(len)?))
+}
@@ -1,20 +1,26 @@
 use crate::{
     Register,
     bytestream::ByteStream,
-    data::{Data, DataArg, RelativeJump},
+    data::{Data, DataArg, FarPointer, RelativeJump},
     parsers,
-    target::{MemoryAddress, Target},
+    target::{FarMemory, MemoryAddress, SizedMemory, Target},
 };
 use anyhow::anyhow;
 use derive_more::Display;
 use std::{fmt::Display, io::Read};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum Mnemonic {
     Add,
+    Adc,
     Mov,
     Sub,
+    Sbb,
     Cmp,
+    And,
+    Test,
+    Or,
+    Xor,
     Jnz,
     Je,
     Jl,
@@ -35,6 +41,66 @@ pub(crate) enum Mnemonic {
     Loopz,
     Loopnz,
     Jcxz,
+    Xlat,
+    Hlt,
+    In,
+    Out,
+    Lea,
+    Lds,
+    Les,
+    Lahf,
+    Sahf,
+    Push,
+    Pop,
+    Pushf,
+    Popf,
+    Inc,
+    Dec,
+    Not,
+    Neg,
+    Mul,
+    Imul,
+    Div,
+    Idiv,
+    Shl,
+    Shr,
+    Sar,
+    Rol,
+    Ror,
+    Rcl,
+    Rcr,
+    Movsb,
+    Movsw,
+    Cmpsb,
+    Cmpsw,
+    Scasb,
+    Scasw,
+    Lodsb,
+    Lodsw,
+    Stosb,
+    Stosw,
+    Call,
+    Ret,
+    Retf,
+    Jmp,
+    Int,
+    Int3,
+    Into,
+    Iret,
+    Clc,
+    Cmc,
+    Stc,
+    Cld,
+    Std,
+    Cli,
+    Sti,
+    Esc,
+    Aaa,
+    Daa,
+    Aas,
+    Das,
+    Aam,
+    Aad,
 }
 
 impl Display for Mnemonic {
@@ -47,9 +113,15 @@ impl Mnemonic {
     fn as_str(&self) -> &str {
         match self {
             Mnemonic::Add => "add",
+            Mnemonic::Adc => "adc",
             Mnemonic::Mov => "mov",
             Mnemonic::Sub => "sub",
+            Mnemonic::Sbb => "sbb",
             Mnemonic::Cmp => "cmp",
+            Mnemonic::And => "and",
+            Mnemonic::Test => "test",
+            Mnemonic::Or => "or",
+            Mnemonic::Xor => "xor",
             Mnemonic::Jnz => "jne",
             Mnemonic::Je => "je",
             Mnemonic::Jl => "jl",
@@ -70,6 +142,66 @@ impl Mnemonic {
             Mnemonic::Loopz => "loopz",
             Mnemonic::Loopnz => "loopnz",
             Mnemonic::Jcxz => "jcxz",
+            Mnemonic::Xlat => "xlat",
+            Mnemonic::Hlt => "hlt",
+            Mnemonic::In => "in",
+            Mnemonic::Out => "out",
+            Mnemonic::Lea => "lea",
+            Mnemonic::Lds => "lds",
+            Mnemonic::Les => "les",
+            Mnemonic::Lahf => "lahf",
+            Mnemonic::Sahf => "sahf",
+            Mnemonic::Push => "push",
+            Mnemonic::Pop => "pop",
+            Mnemonic::Pushf => "pushf",
+            Mnemonic::Popf => "popf",
+            Mnemonic::Inc => "inc",
+            Mnemonic::Dec => "dec",
+            Mnemonic::Not => "not",
+            Mnemonic::Neg => "neg",
+            Mnemonic::Mul => "mul",
+            Mnemonic::Imul => "imul",
+            Mnemonic::Div => "div",
+            Mnemonic::Idiv => "idiv",
+            Mnemonic::Shl => "shl",
+            Mnemonic::Shr => "shr",
+            Mnemonic::Sar => "sar",
+            Mnemonic::Rol => "rol",
+            Mnemonic::Ror => "ror",
+            Mnemonic::Rcl => "rcl",
+            Mnemonic::Rcr => "rcr",
+            Mnemonic::Movsb => "movsb",
+            Mnemonic::Movsw => "movsw",
+            Mnemonic::Cmpsb => "cmpsb",
+            Mnemonic::Cmpsw => "cmpsw",
+            Mnemonic::Scasb => "scasb",
+            Mnemonic::Scasw => "scasw",
+            Mnemonic::Lodsb => "lodsb",
+            Mnemonic::Lodsw => "lodsw",
+            Mnemonic::Stosb => "stosb",
+            Mnemonic::Stosw => "stosw",
+            Mnemonic::Call => "call",
+            Mnemonic::Ret => "ret",
+            Mnemonic::Retf => "retf",
+            Mnemonic::Jmp => "jmp",
+            Mnemonic::Int => "int",
+            Mnemonic::Int3 => "int3",
+            Mnemonic::Into => "into",
+            Mnemonic::Iret => "iret",
+            Mnemonic::Clc => "clc",
+            Mnemonic::Cmc => "cmc",
+            Mnemonic::Stc => "stc",
+            Mnemonic::Cld => "cld",
+            Mnemonic::Std => "std",
+            Mnemonic::Cli => "cli",
+            Mnemonic::Sti => "sti",
+            Mnemonic::Esc => "esc",
+            Mnemonic::Aaa => "aaa",
+            Mnemonic::Daa => "daa",
+            Mnemonic::Aas => "aas",
+            Mnemonic::Das => "das",
+            Mnemonic::Aam => "aam",
+            Mnemonic::Aad => "aad",
         }
     }
 }
@@ -79,9 +211,12 @@ enum_with_matching_struct! {
     pub enum Operand {
         Register,
         MemoryAddress,
+        SizedMemory,
+        FarMemory,
         DataArg,
         Data,
         RelativeJump,
+        FarPointer,
     }
 }
 
@@ -106,6 +241,18 @@ impl From<MemoryAddress> for Operand {
     }
 }
 
+impl From<SizedMemory> for Operand {
+    fn from(m: SizedMemory) -> Self {
+        Self::SizedMemory(m)
+    }
+}
+
+impl From<FarMemory> for Operand {
+    fn from(m: FarMemory) -> Self {
+        Self::FarMemory(m)
+    }
+}
+
 impl From<Data> for Operand {
     fn from(d: Data) -> Self {
         Self::Data(d)
@@ -124,54 +271,248 @@ impl From<RelativeJump> for Operand {
     }
 }
 
+impl From<FarPointer> for Operand {
+    fn from(p: FarPointer) -> Self {
+        Self::FarPointer(p)
+    }
+}
+
 pub(crate) type Operands = (Option<Operand>, Option<Operand>);
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub(crate) enum Rep {
+    #[display("rep")]
+    Rep,
+    #[display("repne")]
+    Repne,
+}
+
 #[derive(Debug)]
 pub(crate) struct Inst {
     pub(crate) mnemonic: Mnemonic,
     pub(crate) operands: Operands,
+    pub(crate) rep: Option<Rep>,
+    pub(crate) lock: bool,
+    /// Segment register named by an override prefix, replacing the default for memory operands.
+    pub(crate) segment: Option<Register>,
+}
+
+/// How an overrides file asks for an instruction's immediate, or its direct address when it has
+/// no immediate, to be written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OperandFormat {
+    Hex,
+    /// The value is an address: write it as the label the listing puts there.
+    Label,
+}
+
+impl OperandFormat {
+    pub(crate) fn label(address: usize) -> String {
+        format!("label_{address:04x}")
+    }
+
+    fn write(self, f: &mut std::fmt::Formatter<'_>, value: u16) -> std::fmt::Result {
+        match self {
+            OperandFormat::Hex => write!(f, "{value:#x}"),
+            OperandFormat::Label => f.write_str(&Self::label(value.into())),
+        }
+    }
+}
+
+/// An instruction written with an override's operand format.
+struct Formatted<'a>(&'a Inst, OperandFormat);
+
+impl Display for Formatted<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.write(f, Some(self.1))
+    }
 }
 
 impl Display for Inst {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write(f, None)
+    }
+}
+
+impl Inst {
+    fn write(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        format: Option<OperandFormat>,
+    ) -> std::fmt::Result {
+        let formatted = format.and_then(|format| Some((self.formattable()?, format)));
+        if self.lock {
+            f.write_str("lock ")?;
+        }
+        if let Some(rep) = self.rep {
+            write!(f, "{rep} ")?;
+        }
+        // With no memory operand to attach it to, an override is written as a prefix
+        if let Some(segment) = self.segment
+            && ![&self.operands.0, &self.operands.1].iter().any(|op| {
+                matches!(
+                    op,
+                    Some(
+                        Operand::MemoryAddress(_) | Operand::SizedMemory(_) | Operand::FarMemory(_)
+                    )
+                )
+            })
+        {
+            write!(f, "{segment} ")?;
+        }
         f.write_str(self.mnemonic.as_str())?;
+        let format_of = |ix| formatted.filter(|&((at, _), _)| at == ix).map(|(_, f)| f);
         if let Some(op) = &self.operands.0 {
-            write!(f, " {op}")?;
+            f.write_str(" ")?;
+            self.fmt_operand(f, op, false, format_of(0))?;
         }
         if let Some(op) = &self.operands.1 {
+            f.write_str(", ")?;
             // HACK:: this is to match the example printing in one specific place, probably this
             // indicates something wrong with our decoding
-            if matches!(self.mnemonic, Mnemonic::Add)
-                && matches!(self.operands.0, Some(Operand::Register(Register::CX)))
-            {
-                write!(f, ", {op:#}")?;
-            } else {
-                write!(f, ", {op}")?;
-            }
+            let alternate = matches!(self.mnemonic, Mnemonic::Add)
+                && matches!(self.operands.0, Some(Operand::Register(Register::CX)));
+            self.fmt_operand(f, op, alternate, format_of(1))?;
         }
         Ok(())
     }
-}
 
-impl Inst {
-    fn new(mnemonic: Mnemonic, op1: Option<Operand>, op2: Option<Operand>) -> Self {
+    pub(crate) fn formatted(&self, format: OperandFormat) -> impl Display + '_ {
+        Formatted(self, format)
+    }
+
+    /// The operand an override's format applies to: the immediate, or failing that a direct
+    /// address. Returns its index with its value and whether it's a word.
+    pub(crate) fn formattable(&self) -> Option<(usize, (u16, bool))> {
+        let operands = [&self.operands.0, &self.operands.1];
+        let immediate = operands.iter().enumerate().find_map(|(ix, op)| match op {
+            Some(Operand::Data(d)) => Some((ix, (d.into(), d.is_wide()))),
+            Some(Operand::DataArg(d)) => Some((ix, (d.into(), d.data.is_wide()))),
+            _ => None,
+        });
+        immediate.or_else(|| {
+            operands.iter().enumerate().find_map(|(ix, op)| match op {
+                Some(Operand::MemoryAddress(MemoryAddress::Direct(d)))
+                | Some(Operand::SizedMemory(SizedMemory {
+                    address: MemoryAddress::Direct(d),
+                    ..
+                })) => Some((ix, (d.into(), true))),
+                _ => None,
+            })
+        })
+    }
+
+    fn new(
+        mnemonic: Mnemonic,
+        op1: Option<Operand>,
+        op2: Option<Operand>,
+        rep: Option<Rep>,
+        lock: bool,
+        segment: Option<Register>,
+    ) -> Self {
         Self {
             mnemonic,
             operands: (op1, op2),
+            rep,
+            lock,
+            segment,
+        }
+    }
+
+    /// Writes an operand, putting any segment override in front of the brackets of a memory
+    /// operand (e.g. `word es:[bx]`).
+    fn fmt_operand(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        op: &Operand,
+        alternate: bool,
+        format: Option<OperandFormat>,
+    ) -> std::fmt::Result {
+        if let Some(format) = format {
+            return self.fmt_formatted_operand(f, op, format);
+        }
+        let Some(segment) = self.segment else {
+            return if alternate {
+                write!(f, "{op:#}")
+            } else {
+                write!(f, "{op}")
+            };
+        };
+        match op {
+            Operand::MemoryAddress(m) => write!(f, "{segment}:{m}"),
+            Operand::SizedMemory(m) => {
+                let size = if m.is_wide { "word" } else { "byte" };
+                write!(f, "{size} {segment}:{}", m.address)
+            }
+            Operand::FarMemory(m) => write!(f, "far {segment}:{}", m.0),
+            _ if alternate => write!(f, "{op:#}"),
+            _ => write!(f, "{op}"),
+        }
+    }
+
+    /// Writes the operand `formattable` picked, with its value in `format`.
+    fn fmt_formatted_operand(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        op: &Operand,
+        format: OperandFormat,
+    ) -> std::fmt::Result {
+        let size = |is_wide| if is_wide { "word " } else { "byte " };
+        let segment = self.segment.map(|s| format!("{s}:")).unwrap_or_default();
+        match op {
+            Operand::Data(d) => format.write(f, d.into()),
+            Operand::DataArg(d) => {
+                if d.explicit {
+                    f.write_str(size(d.data.is_wide()))?;
+                }
+                format.write(f, d.into())
+            }
+            Operand::MemoryAddress(MemoryAddress::Direct(d)) => {
+                write!(f, "{segment}[")?;
+                format.write(f, d.into())?;
+                f.write_str("]")
+            }
+            Operand::SizedMemory(SizedMemory {
+                is_wide,
+                address: MemoryAddress::Direct(d),
+            }) => {
+                write!(f, "{}{segment}[", size(*is_wide))?;
+                format.write(f, d.into())?;
+                f.write_str("]")
+            }
+            _ => self.fmt_operand(f, op, false, None),
         }
     }
 
     pub(crate) fn parse<T: Read>(bytes: &mut ByteStream<T>) -> anyhow::Result<Option<Self>> {
-        let Some(byte_1) = bytes.maybe_next()? else {
+        let Some(mut byte_1) = bytes.maybe_next()? else {
             return Ok(None);
         };
 
+        let mut rep = None;
+        let mut lock = false;
+        let mut segment = None;
+        loop {
+            match byte_1 {
+                0b11110011 => rep = Some(Rep::Rep),
+                0b11110010 => rep = Some(Rep::Repne),
+                0b11110000 => lock = true,
+                b if b & 0b11100111 == 0b00100110 => {
+                    segment = Some(Register::from_sr(b >> 3 & 0b11)?)
+                }
+                _ => break,
+            }
+            byte_1 = bytes.next()?;
+        }
+
         use Mnemonic::*;
         use parsers::*;
 
         let (mnemonic, (op1, op2)) = match byte_1 {
             b if b >> 2 == 0b000000 => (Add, parse_reg_mem_either_way(b, bytes)?),
             b if b >> 1 == 0b0000010 => (Add, parse_imm_to_acc(b, bytes)?),
+            b if b >> 2 == 0b000100 => (Adc, parse_reg_mem_either_way(b, bytes)?),
+            b if b >> 1 == 0b0001010 => (Adc, parse_imm_to_acc(b, bytes)?),
             b if b >> 2 == 0b100010 => (Mov, parse_reg_mem_either_way(b, bytes)?),
             b if b >> 4 == 0b1011 => (Mov, parse_mov_imm_to_reg(b, bytes)?),
             b if b >> 1 == 0b1100011 => {
@@ -181,20 +522,94 @@ impl Inst {
             b if b >> 1 == 0b1010001 => (Mov, parse_mov_acc_to_mem(b, bytes)?),
             0b10001110 => (Mov, parse_rm_to_sm(bytes)?),
             0b10001100 => (Mov, parse_sm_to_rm(bytes)?),
+            0b10001101 => (Lea, parse_lea(bytes)?),
+            0b11000101 => (Lds, parse_lea(bytes)?),
+            0b11000100 => (Les, parse_lea(bytes)?),
             b if b >> 2 == 0b001010 => (Sub, parse_reg_mem_either_way(b, bytes)?),
             b if b >> 1 == 0b0010110 => (Sub, parse_imm_to_acc(b, bytes)?),
+            b if b >> 2 == 0b000110 => (Sbb, parse_reg_mem_either_way(b, bytes)?),
+            b if b >> 1 == 0b0001110 => (Sbb, parse_imm_to_acc(b, bytes)?),
             b if b >> 2 == 0b001110 => (Cmp, parse_reg_mem_either_way(b, bytes)?),
             b if b >> 1 == 0b0011110 => (Cmp, parse_imm_to_acc(b, bytes)?),
+            b if b >> 2 == 0b001000 => (And, parse_reg_mem_either_way(b, bytes)?),
+            b if b >> 1 == 0b0010010 => (And, parse_imm_to_acc(b, bytes)?),
+            b if b >> 1 == 0b1000010 => (Test, parse_reg_mem_either_way(b, bytes)?),
+            b if b >> 1 == 0b1010100 => (Test, parse_imm_to_acc(b, bytes)?),
+            b if b >> 2 == 0b000010 => (Or, parse_reg_mem_either_way(b, bytes)?),
+            b if b >> 1 == 0b0000110 => (Or, parse_imm_to_acc(b, bytes)?),
+            b if b >> 2 == 0b001100 => (Xor, parse_reg_mem_either_way(b, bytes)?),
+            b if b >> 1 == 0b0011010 => (Xor, parse_imm_to_acc(b, bytes)?),
             b if b >> 2 == 0b100000 => {
                 let byte_2 = bytes.next()?;
                 let op = byte_2 >> 3 & 0b111;
                 match op {
                     0b000 => (Add, parse_imm_to_reg_mem(b, byte_2, bytes, true)?),
+                    0b001 => (Or, parse_imm_to_reg_mem(b, byte_2, bytes, true)?),
+                    0b010 => (Adc, parse_imm_to_reg_mem(b, byte_2, bytes, true)?),
+                    0b011 => (Sbb, parse_imm_to_reg_mem(b, byte_2, bytes, true)?),
+                    0b100 => (And, parse_imm_to_reg_mem(b, byte_2, bytes, true)?),
                     0b101 => (Sub, parse_imm_to_reg_mem(b, byte_2, bytes, true)?),
+                    0b110 => (Xor, parse_imm_to_reg_mem(b, byte_2, bytes, true)?),
                     0b111 => (Cmp, parse_imm_to_reg_mem(b, byte_2, bytes, true)?),
                     _ => return Err(anyhow!("usupported op: {op:03b}")),
                 }
             }
+            b if b >> 3 == 0b01010 => (Push, parse_reg(b)?),
+            b if b >> 3 == 0b01011 => (Pop, parse_reg(b)?),
+            b if b & 0b11100111 == 0b00000110 => (Push, parse_seg_reg(b)?),
+            // 0x0f would be `pop cs`, which the 8086 doesn't have
+            b if b & 0b11100111 == 0b00000111 && b != 0b00001111 => (Pop, parse_seg_reg(b)?),
+            0b10001111 => {
+                let byte_2 = bytes.next()?;
+                match byte_2 >> 3 & 0b111 {
+                    0b000 => (Pop, parse_reg_mem(0b1, byte_2, bytes)?),
+                    op => return Err(anyhow!("usupported op: {op:03b}")),
+                }
+            }
+            b if b >> 3 == 0b01000 => (Inc, parse_reg(b)?),
+            b if b >> 3 == 0b01001 => (Dec, parse_reg(b)?),
+            b if b >> 1 == 0b1111111 => {
+                let byte_2 = bytes.next()?;
+                let op = byte_2 >> 3 & 0b111;
+                match op {
+                    0b000 => (Inc, parse_reg_mem(b, byte_2, bytes)?),
+                    0b001 => (Dec, parse_reg_mem(b, byte_2, bytes)?),
+                    0b010 if b & 0b1 == 1 => (Call, parse_reg_mem(b, byte_2, bytes)?),
+                    0b011 if b & 0b1 == 1 => (Call, parse_far_mem(byte_2, bytes)?),
+                    0b100 if b & 0b1 == 1 => (Jmp, parse_reg_mem(b, byte_2, bytes)?),
+                    0b101 if b & 0b1 == 1 => (Jmp, parse_far_mem(byte_2, bytes)?),
+                    0b110 if b & 0b1 == 1 => (Push, parse_reg_mem(b, byte_2, bytes)?),
+                    _ => return Err(anyhow!("usupported op: {op:03b}")),
+                }
+            }
+            b if b >> 1 == 0b1111011 => {
+                let byte_2 = bytes.next()?;
+                let op = byte_2 >> 3 & 0b111;
+                match op {
+                    0b000 => (Test, parse_imm_to_reg_mem(b, byte_2, bytes, false)?),
+                    0b010 => (Not, parse_reg_mem(b, byte_2, bytes)?),
+                    0b011 => (Neg, parse_reg_mem(b, byte_2, bytes)?),
+                    0b100 => (Mul, parse_reg_mem(b, byte_2, bytes)?),
+                    0b101 => (Imul, parse_reg_mem(b, byte_2, bytes)?),
+                    0b110 => (Div, parse_reg_mem(b, byte_2, bytes)?),
+                    0b111 => (Idiv, parse_reg_mem(b, byte_2, bytes)?),
+                    _ => return Err(anyhow!("usupported op: {op:03b}")),
+                }
+            }
+            b if b >> 2 == 0b110100 => {
+                let byte_2 = bytes.next()?;
+                let op = byte_2 >> 3 & 0b111;
+                match op {
+                    0b000 => (Rol, parse_shift(b, byte_2, bytes)?),
+                    0b001 => (Ror, parse_shift(b, byte_2, bytes)?),
+                    0b010 => (Rcl, parse_shift(b, byte_2, bytes)?),
+                    0b011 => (Rcr, parse_shift(b, byte_2, bytes)?),
+                    0b100 => (Shl, parse_shift(b, byte_2, bytes)?),
+                    0b101 => (Shr, parse_shift(b, byte_2, bytes)?),
+                    0b111 => (Sar, parse_shift(b, byte_2, bytes)?),
+                    _ => return Err(anyhow!("usupported op: {op:03b}")),
+                }
+            }
             0b01110100 => (Je, parse_ip_inc_8(bytes.next()?)),
             0b01111100 => (Jl, parse_ip_inc_8(bytes.next()?)),
             0b01110101 => (Jnz, parse_ip_inc_8(bytes.next()?)),
@@ -215,10 +630,221 @@ impl Inst {
             0b11100001 => (Loopz, parse_ip_inc_8(bytes.next()?)),
             0b11100000 => (Loopnz, parse_ip_inc_8(bytes.next()?)),
             0b11100011 => (Jcxz, parse_ip_inc_8(bytes.next()?)),
+            0b11101000 => (Call, parse_ip_inc_16(bytes, false)?),
+            0b10011010 => (Call, parse_far_ptr(bytes)?),
+            0b11101011 => (Jmp, parse_ip_inc_8(bytes.next()?)),
+            0b11101001 => (Jmp, parse_ip_inc_16(bytes, true)?),
+            0b11101010 => (Jmp, parse_far_ptr(bytes)?),
+            0b11001101 => (Int, (Some(Data::Byte(bytes.next()?).into()), None)),
+            0b11001100 => (Int3, (None, None)),
+            0b11001110 => (Into, (None, None)),
+            0b11001111 => (Iret, (None, None)),
+            0b11110100 => (Hlt, (None, None)),
+            0b11111000 => (Clc, (None, None)),
+            0b11110101 => (Cmc, (None, None)),
+            0b11111001 => (Stc, (None, None)),
+            0b11111100 => (Cld, (None, None)),
+            0b11111101 => (Std, (None, None)),
+            0b11111010 => (Cli, (None, None)),
+            0b11111011 => (Sti, (None, None)),
+            b if b >> 3 == 0b11011 => (Esc, parse_esc(b, bytes)?),
+            0b00110111 => (Aaa, (None, None)),
+            0b00100111 => (Daa, (None, None)),
+            0b00111111 => (Aas, (None, None)),
+            0b00101111 => (Das, (None, None)),
+            0b11010100 => (Aam, parse_ascii_adjust_base(bytes.next()?)),
+            0b11010101 => (Aad, parse_ascii_adjust_base(bytes.next()?)),
+            0b11000011 => (Ret, (None, None)),
+            0b11000010 => (Ret, parse_imm16(bytes)?),
+            0b11001011 => (Retf, (None, None)),
+            0b11001010 => (Retf, parse_imm16(bytes)?),
+            0b11010111 => (Xlat, (None, None)),
+            b if b >> 1 == 0b1110010 => (In, parse_port_fixed(b, bytes)?),
+            b if b >> 1 == 0b1110110 => (In, parse_port_variable(b)),
+            b if b >> 1 == 0b1110011 => {
+                let (acc, port) = parse_port_fixed(b, bytes)?;
+                (Out, (port, acc))
+            }
+            b if b >> 1 == 0b1110111 => {
+                let (acc, port) = parse_port_variable(b);
+                (Out, (port, acc))
+            }
+            0b10011111 => (Lahf, (None, None)),
+            0b10011110 => (Sahf, (None, None)),
+            0b10100100 => (Movsb, (None, None)),
+            0b10100101 => (Movsw, (None, None)),
+            0b10100110 => (Cmpsb, (None, None)),
+            0b10100111 => (Cmpsw, (None, None)),
+            0b10101110 => (Scasb, (None, None)),
+            0b10101111 => (Scasw, (None, None)),
+            0b10101100 => (Lodsb, (None, None)),
+            0b10101101 => (Lodsw, (None, None)),
+            0b10101010 => (Stosb, (None, None)),
+            0b10101011 => (Stosw, (None, None)),
+            0b10011100 => (Pushf, (None, None)),
+            0b10011101 => (Popf, (None, None)),
             _ => {
                 return Err(anyhow!("unsupported opcode in byte: {byte_1:08b}"));
             }
         };
-        Ok(Some(Self::new(mnemonic, op1, op2)))
+        Ok(Some(Self::new(mnemonic, op1, op2, rep, lock, segment)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufReader, Cursor};
+
+    /// Decodes every instruction in `bytes` and checks each is written as expected.
+    fn assert_decodes(bytes: &[u8], expected: &[&str]) {
+        let mut stream = ByteStream {
+            reader: BufReader::new(Cursor::new(bytes.to_vec())),
+        };
+        let mut decoded = vec![];
+        while let Some(inst) = Inst::parse(&mut stream).unwrap() {
+            decoded.push(inst.to_string());
+        }
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn decodes_table_and_address_forms() {
+        assert_decodes(
+            &[0xD7, 0x8D, 0x40, 0x02, 0xC5, 0x3F, 0xC4, 0x78, 0x0A],
+            &[
+                "xlat",
+                "lea ax, [bx + si + 2]",
+                "lds di, [bx]",
+                "les di, [bx + si + 10]",
+            ],
+        );
+    }
+
+    #[test]
+    fn decodes_flag_transfers() {
+        assert_decodes(
+            &[
+                0x9F, 0x9E, 0x9C, 0x9D, 0xF8, 0xF5, 0xF9, 0xFC, 0xFD, 0xFA, 0xFB,
+            ],
+            &[
+                "lahf", "sahf", "pushf", "popf", "clc", "cmc", "stc", "cld", "std", "cli", "sti",
+            ],
+        );
+    }
+
+    #[test]
+    fn decodes_arithmetic() {
+        assert_decodes(
+            &[
+                0x14, 0x05, 0x1C, 0x05, 0x40, 0xFE, 0xC0, 0x4B, 0xF7, 0xD8, 0xF6, 0xE3, 0xF7, 0xEB,
+                0xF7, 0xF1, 0xF6, 0xFB,
+            ],
+            &[
+                "adc al, 5",
+                "sbb al, 5",
+                "inc ax",
+                "inc al",
+                "dec bx",
+                "neg ax",
+                "mul bl",
+                "imul bx",
+                "div cx",
+                "idiv bl",
+            ],
+        );
+    }
+
+    #[test]
+    fn decodes_logic_shifts_and_rotates() {
+        assert_decodes(
+            &[
+                0xF7, 0xD0, 0xD1, 0xE0, 0xD3, 0xE8, 0xD0, 0xF8, 0xD1, 0xC0, 0xD1, 0xC8, 0xD0, 0xD0,
+                0xD3, 0xD8, 0x21, 0xD8, 0xA8, 0x01, 0x09, 0xD8, 0x31, 0xC0,
+            ],
+            &[
+                "not ax",
+                "shl ax, 1",
+                "shr ax, cl",
+                "sar al, 1",
+                "rol ax, 1",
+                "ror ax, 1",
+                "rcl al, 1",
+                "rcr ax, cl",
+                "and ax, bx",
+                "test al, 1",
+                "or ax, bx",
+                "xor ax, ax",
+            ],
+        );
+    }
+
+    #[test]
+    fn decodes_string_instructions_and_prefixes() {
+        assert_decodes(
+            &[0xF3, 0xA4, 0xF2, 0xA6, 0xAE, 0xAD, 0xAA, 0xF0, 0xFF, 0x07],
+            &[
+                "rep movsb",
+                "repne cmpsb",
+                "scasb",
+                "lodsw",
+                "stosb",
+                "lock inc word [bx]",
+            ],
+        );
+    }
+
+    #[test]
+    fn decodes_control_transfers() {
+        assert_decodes(
+            &[
+                0xE8, 0x00, 0x00, 0xFF, 0xD3, 0x9A, 0x34, 0x12, 0x00, 0x10, 0xC3, 0xC2, 0x04, 0x00,
+                0xCB, 0xEB, 0x00, 0xFF, 0xE3, 0xEA, 0x34, 0x12, 0x00, 0x10,
+            ],
+            &[
+                "call $+3",
+                "call bx",
+                "call 4096:4660",
+                "ret",
+                "ret 4",
+                "retf",
+                "jmp $+2",
+                "jmp bx",
+                "jmp 4096:4660",
+            ],
+        );
+    }
+
+    #[test]
+    fn decodes_interrupts_and_escapes() {
+        assert_decodes(
+            &[0xCD, 0x21, 0xCC, 0xCE, 0xCF, 0xD8, 0x0F],
+            &["int 33", "int3", "into", "iret", "esc 1, [bx]"],
+        );
+    }
+
+    #[test]
+    fn decodes_bcd_adjusts() {
+        assert_decodes(
+            &[0x37, 0x27, 0x3F, 0x2F, 0xD4, 0x0A, 0xD5, 0x0A, 0xD4, 0x10],
+            &["aaa", "daa", "aas", "das", "aam", "aad", "aam 16"],
+        );
+    }
+
+    #[test]
+    fn writes_operand_formats() {
+        let mut stream = ByteStream {
+            reader: BufReader::new(Cursor::new(vec![0xBB, 0x0A, 0x00, 0xA1, 0x0C, 0x00])),
+        };
+        let mov_bx = Inst::parse(&mut stream).unwrap().unwrap();
+        let mov_ax = Inst::parse(&mut stream).unwrap().unwrap();
+        assert_eq!(
+            mov_bx.formatted(OperandFormat::Hex).to_string(),
+            "mov bx, 0xa"
+        );
+        assert_eq!(
+            mov_ax.formatted(OperandFormat::Label).to_string(),
+            "mov ax, [label_000c]"
+        );
     }
 }
@@ -0,0 +1,131 @@
+use crate::pic::Pic;
+use std::fmt::Debug;
+
+/// Devices reachable through the port address space. A word access is two byte accesses.
+pub(crate) trait IoBus: Debug {
+    fn port_in(&mut self, port: u16) -> u8;
+    fn port_out(&mut self, port: u16, value: u8);
+
+    fn tick(&mut self, _clocks: u32) {}
+
+    /// The vector of a hardware interrupt waiting for the CPU, acknowledging it.
+    fn interrupt(&mut self) -> Option<u8> {
+        None
+    }
+
+    /// Ends the interrupt on `vector` as the handler's EOI would, for one nothing handled.
+    fn end_interrupt(&mut self, _vector: u8) {}
+
+    fn reset(&mut self) {}
+}
+
+/// A bus with nothing attached: reads float high and writes go nowhere.
+#[derive(Debug, Default)]
+pub(crate) struct UnconnectedBus;
+
+impl IoBus for UnconnectedBus {
+    fn port_in(&mut self, _port: u16) -> u8 {
+        0xFF
+    }
+
+    fn port_out(&mut self, _port: u16, _value: u8) {}
+}
+
+pub(crate) trait Device: Debug {
+    fn claims(&self, port: u16) -> bool;
+    fn port_in(&mut self, port: u16) -> u8;
+    fn port_out(&mut self, port: u16, value: u8);
+
+    /// Returns whether the device raised its interrupt line.
+    fn tick(&mut self, _clocks: u32) -> bool {
+        false
+    }
+
+    fn reset(&mut self) {}
+}
+
+/// A set of devices sharing the port space. Ports nobody claims float high.
+#[derive(Debug, Default)]
+pub(crate) struct DeviceBus {
+    devices: Vec<(Box<dyn Device>, Option<u8>)>,
+    pic: Option<Pic>,
+    pending: u8,
+}
+
+impl DeviceBus {
+    pub(crate) fn with_device(mut self, device: impl Device + 'static, irq: Option<u8>) -> Self {
+        self.devices.push((Box::new(device), irq));
+        self
+    }
+
+    pub(crate) fn with_pic(mut self, pic: Pic) -> Self {
+        self.pic = Some(pic);
+        self
+    }
+
+    fn device_for(&mut self, port: u16) -> Option<&mut (dyn Device + 'static)> {
+        if let Some(pic) = self.pic.as_mut().filter(|pic| pic.claims(port)) {
+            return Some(pic);
+        }
+        self.devices
+            .iter_mut()
+            .map(|(device, _)| device.as_mut())
+            .find(|device| device.claims(port))
+    }
+}
+
+impl IoBus for DeviceBus {
+    fn port_in(&mut self, port: u16) -> u8 {
+        match self.device_for(port) {
+            Some(device) => device.port_in(port),
+            None => 0xFF,
+        }
+    }
+
+    fn port_out(&mut self, port: u16, value: u8) {
+        if let Some(device) = self.device_for(port) {
+            device.port_out(port, value);
+        }
+    }
+
+    fn tick(&mut self, clocks: u32) {
+        for (device, irq) in &mut self.devices {
+            if device.tick(clocks)
+                && let Some(line) = irq
+            {
+                match &mut self.pic {
+                    Some(pic) => pic.request(*line),
+                    None => self.pending |= 1 << *line,
+                }
+            }
+        }
+    }
+
+    fn interrupt(&mut self) -> Option<u8> {
+        if let Some(pic) = &mut self.pic {
+            return pic.acknowledge();
+        }
+        if self.pending == 0 {
+            return None;
+        }
+        let line = self.pending.trailing_zeros() as u8;
+        self.pending &= !(1 << line);
+        Some(8 + line)
+    }
+
+    fn end_interrupt(&mut self, vector: u8) {
+        if let Some(pic) = &mut self.pic {
+            pic.end_vector(vector);
+        }
+    }
+
+    fn reset(&mut self) {
+        for (device, _) in &mut self.devices {
+            device.reset();
+        }
+        if let Some(pic) = &mut self.pic {
+            pic.reset();
+        }
+        self.pending = 0;
+    }
+}
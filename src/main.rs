@@ -1,68 +1,688 @@
 use anyhow::anyhow;
+use bundle::CrashBundle;
 use bytestream::ByteStream;
-use clap::Parser;
-use computer::ExeResult;
-use instruction::{Inst, Mnemonic};
-use register::Register;
+use clap::{Parser, Subcommand};
+use clocks::Cpu;
+use computer::{Computer, ExeResult, FORMAT_VERSION};
+use console::ConsoleDevice;
+use data::FarPointer;
+use debugger::Debugger;
+use instruction::{Inst, Mnemonic, OperandFormat};
+use iobus::DeviceBus;
+use memory::{Memory, MemoryFile, MemoryRange};
+use overrides::{Overrides, RegionKind, parse_offset};
+use pic::Pic;
+use pit::Pit;
+use register::{Register, RegisterValue};
+use state::MachineState;
 use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    env, fs,
     fs::File,
-    io::{BufReader, BufWriter, Write},
-    path::PathBuf,
+    io::{self, BufReader, BufWriter, Cursor, IsTerminal, Write},
+    ops::Range,
+    path::{Path, PathBuf},
 };
+use uart::Uart;
+use verify::ReferenceTrace;
 
 #[macro_use]
 mod macros;
 
+mod bundle;
 mod bytestream;
+mod clocks;
 mod computer;
+mod console;
 mod data;
+mod debugger;
+mod entry;
+mod flagcheck;
+mod gdbstub;
 mod instruction;
+mod iobus;
+mod memory;
+mod overrides;
 mod parsers;
+mod pic;
+mod pit;
 mod register;
+mod state;
 mod target;
+mod uart;
+mod verify;
+
+/// Where DOS would put a .COM program: past its 256-byte program segment prefix.
+const COM_ORIGIN: u16 = 0x100;
+
+/// How many of the most recently executed instructions a crash bundle keeps.
+const RECENT_INSTRUCTIONS: usize = 32;
 
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
-    #[arg(value_name = "BINFILE")]
-    infile: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[arg(value_name = "BINFILE", required = true)]
+    infile: Option<PathBuf>,
     #[arg(short, long, value_name = "ASMFILE")]
     outfile: Option<PathBuf>,
     #[arg(short, long)]
     print_ip: bool,
+    /// Guess where code starts in a raw image and disassemble from there, emitting the bytes
+    /// before it as data
+    #[arg(long)]
+    detect_entry: bool,
+    /// File of code/data region and entry point overrides applied when disassembling; a
+    /// detected entry point is saved back to it
+    #[arg(long, value_name = "FILE")]
+    overrides: Option<PathBuf>,
+    /// Run the program twice and verify both traces match exactly
+    #[arg(long)]
+    audit_determinism: bool,
+    /// Warn when a conditional jump reads flags that nothing has set or that were left undefined
+    #[arg(long)]
+    warn_flags: bool,
+    /// Load the program at SEG:0, or SEG:0x100 after a PSP for a `.com` file, and start every
+    /// segment register at SEG. 0 gives the course's flat layout, where the program overlaps
+    /// the interrupt vector table
+    #[arg(long, value_name = "SEG", value_parser = parse_word, default_value = "0x1000")]
+    load_segment: u16,
+    /// Start a register with a value instead of zero, e.g. `--reg sp=0xfffe` (may be repeated)
+    #[arg(long, value_name = "REG=VALUE")]
+    reg: Vec<RegisterValue>,
+    /// Report changes to a register, e.g. `--watch ah` (may be repeated)
+    #[arg(long, value_name = "REG")]
+    watch: Vec<Register>,
+    /// Report every read and write touching a range of memory, e.g. `--watch-mem 0x1000..0x1010`
+    /// (may be repeated)
+    #[arg(long, value_name = "START..END")]
+    watch_mem: Vec<MemoryRange>,
+    /// Emit output in the given format version and record it in the output header; 1 is the
+    /// course's trace layout, without anything added since
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..=FORMAT_VERSION as i64))]
+    format_version: Option<u32>,
+    /// If the simulation fails, write a crash bundle that `open-bundle` can replay
+    #[arg(long, value_name = "BUNDLE")]
+    crash_bundle: Option<PathBuf>,
+    /// Show each instruction's estimated clock count and the running total
+    #[arg(long)]
+    show_clocks: bool,
+    /// List how many times each mnemonic ran, and its clocks with `--show-clocks`
+    #[arg(long)]
+    stats: bool,
+    /// CPU to estimate clocks for with `--show-clocks`; repeat to compare side by side
+    #[arg(long, value_name = "CPU", default_value = "8086")]
+    cpu: Vec<Cpu>,
+    /// Copy FILE into memory at START before running, e.g. `--load table.bin@0x1000` (may be
+    /// repeated; a `..END` bound truncates the file)
+    #[arg(long, value_name = "FILE@START")]
+    load: Vec<MemoryFile>,
+    /// Give up with an error after executing N instructions
+    #[arg(long, value_name = "N")]
+    max_instructions: Option<u64>,
+    /// Log every memory load and store the program makes to FILE
+    #[arg(long, value_name = "FILE")]
+    memory_log: Option<PathBuf>,
+    /// Step through the program at an interactive prompt instead of tracing it
+    #[arg(long)]
+    debug: bool,
+    /// Wait for GDB to connect on PORT and let it control the program
+    #[arg(long, value_name = "PORT")]
+    gdb: Option<u16>,
+    /// Connect a console to ports PORT (data) and PORT+1 (status) so `in` reads stdin and `out`
+    /// writes stdout
+    #[arg(long, value_name = "PORT", value_parser = parse_word)]
+    console_port: Option<u16>,
+    /// Attach an 8253 timer on ports 0x40-0x43 whose channel 0 raises IRQ0 (INT 8)
+    #[arg(long)]
+    pit: bool,
+    /// Route device interrupts through an 8259 interrupt controller on ports 0x20-0x21, so
+    /// they can be remapped and masked and each handler must send an EOI
+    #[arg(long)]
+    pic: bool,
+    /// Attach an 8250 UART as COM1 (ports 0x3F8-0x3FF, IRQ4), bridged to TARGET: `stdio`, or a
+    /// path such as a pty or FIFO to read and write
+    #[arg(long, value_name = "TARGET")]
+    serial: Option<PathBuf>,
+    /// Stop when IP reaches ADDR: pause if run from a terminal, otherwise print the registers
+    /// and carry on (may be repeated)
+    #[arg(long = "break", value_name = "ADDR", value_parser = parse_word)]
+    breakpoints: Vec<u16>,
+    /// Pause after every instruction until Enter is pressed, as if the trap flag were set
+    #[arg(long)]
+    single_step: bool,
+    /// Write the final contents of memory to FILE, or just START..END with `FILE@START..END`
+    #[arg(long, value_name = "FILE")]
+    dump: Option<MemoryFile>,
+    /// Compare the trace against a known-good one and stop at the first line that differs
+    #[arg(long, value_name = "TRACEFILE")]
+    verify: Option<PathBuf>,
+    /// Save the registers, flags and memory to FILE when the run stops, even if it fails
+    #[arg(long, value_name = "FILE")]
+    save_state: Option<PathBuf>,
+    /// Resume from a state saved with `--save-state` instead of starting afresh
+    #[arg(long, value_name = "FILE")]
+    restore_state: Option<PathBuf>,
+    /// List the words on the stack after the final registers
+    #[arg(long)]
+    dump_stack: bool,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Re-run the program captured in a crash bundle with its original options
+    OpenBundle {
+        #[arg(value_name = "BUNDLE")]
+        bundle: PathBuf,
+    },
+}
+
+impl Cli {
+    fn infile(&self) -> anyhow::Result<&PathBuf> {
+        self.infile.as_ref().ok_or(anyhow!("missing in file"))
+    }
+}
+
+/// What a run starts from besides its options: the program, the `--load` images and any saved
+/// state it resumes. A crash bundle carries all of it, so a replay needs none of the files.
+#[derive(Debug)]
+struct Inputs {
+    image: Vec<u8>,
+    loads: Vec<(u32, Vec<u8>)>,
+    restored: Option<MachineState>,
+}
+
+impl Inputs {
+    fn read(cli: &Cli, image: Vec<u8>) -> anyhow::Result<Self> {
+        let mut loads = vec![];
+        for load in &cli.load {
+            let mut data = fs::read(&load.path)?;
+            if let Some(end) = load.end {
+                data.truncate(end.saturating_sub(load.start));
+            }
+            loads.push((load.start as u32, data));
+        }
+        let restored = cli
+            .restore_state
+            .as_deref()
+            .map(MachineState::read_from)
+            .transpose()?;
+        Ok(Self {
+            image,
+            loads,
+            restored,
+        })
+    }
 }
 
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
-    let mut byte_stream = ByteStream {
-        reader: BufReader::new(File::open(&cli.infile)?),
+    if let Some(Command::OpenBundle { bundle }) = &cli.command {
+        return open_bundle(bundle);
+    }
+
+    let image = fs::read(cli.infile()?)?;
+
+    if let Some(out_file_path) = &cli.outfile {
+        return disassemble(&cli, &image, out_file_path);
+    }
+
+    let inputs = Inputs::read(&cli, image)?;
+
+    if cli.debug {
+        let mut computer = setup(&cli, &inputs)?.with_journal(true);
+        return Debugger::new(&mut computer, cli.breakpoints.clone(), cli.print_ip).run();
+    }
+
+    if let Some(port) = cli.gdb {
+        let mut computer = setup(&cli, &inputs)?;
+        return gdbstub::serve(&mut computer, cli.breakpoints.clone(), port);
+    }
+
+    if cli.audit_determinism {
+        return audit_determinism(&cli, &inputs);
+    }
+
+    print_header(&cli);
+    println!("--- test\\{} execution ---", infile_name(&cli)?);
+    let mut reference = cli
+        .verify
+        .as_deref()
+        .map(ReferenceTrace::read_from)
+        .transpose()?;
+    run(&cli, &inputs, |line| {
+        println!("{line}");
+        match &mut reference {
+            Some(reference) => reference.check(&line),
+            None => Ok(()),
+        }
+    })?;
+    if let Some(reference) = &reference {
+        let matched = reference.finish()?;
+        eprintln!("trace matches the reference: {matched} lines");
+    }
+    Ok(())
+}
+
+fn disassemble(cli: &Cli, image: &[u8], out_file_path: &Path) -> anyhow::Result<()> {
+    let overrides = match &cli.overrides {
+        Some(path) => Overrides::read_from(path)?,
+        None => Overrides::default(),
     };
+    let entry = match overrides.entry {
+        Some(entry) => entry,
+        None if cli.detect_entry => {
+            let candidate = entry::detect_entry_point(image).ok_or(anyhow!("empty image"))?;
+            eprintln!(
+                "entry point: {:#x} (score {}, {} instructions{}{})",
+                candidate.offset,
+                candidate.score,
+                candidate.instructions,
+                if candidate.prologue { ", prologue" } else { "" },
+                if candidate.referenced {
+                    ", jumped to"
+                } else {
+                    ""
+                },
+            );
+            if let Some(path) = &cli.overrides {
+                Overrides::save_entry(path, candidate.offset)?;
+            }
+            candidate.offset
+        }
+        None => 0,
+    };
+
+    let mut out_file = BufWriter::new(File::create(out_file_path)?);
+    writeln!(out_file, ";{}", infile_name(cli)?)?;
+    if let Some(version) = cli.format_version {
+        writeln!(out_file, ";format-version: {version}")?;
+    }
+    writeln!(out_file)?;
+    writeln!(out_file, "bits 16")?;
+    writeln!(out_file)?;
+
+    // Decode everything first, so labels for addresses can be placed before anything's written
+    let mut lines = vec![];
+    let mut pos = 0;
+    while pos < image.len() {
+        let kind = overrides.kind_at(pos, entry);
+        let end = (pos..image.len())
+            .find(|&offset| overrides.kind_at(offset, entry) != kind)
+            .unwrap_or(image.len());
+        match kind {
+            RegionKind::Data => lines.push((pos, Line::Data(pos..end))),
+            RegionKind::Code => {
+                let mut byte_stream = program_stream(&image[pos..end]);
+                loop {
+                    let start = pos + byte_stream.get_iptr()? as usize;
+                    match Inst::parse(&mut byte_stream) {
+                        Ok(Some(instruction)) => lines.push((start, Line::Code(instruction))),
+                        Ok(None) => break,
+                        // an instruction running into a data region is emitted as data instead
+                        Err(e) if end < image.len() => {
+                            eprintln!("warning: {start:#x}: {e}, emitting as data");
+                            lines.push((start, Line::Data(start..end)));
+                            break;
+                        }
+                        Err(e) => return Err(e),
+                    }
+                }
+            }
+        }
+        pos = end;
+    }
+
+    let mut formats = HashMap::new();
+    let mut labels = BTreeSet::new();
+    for (offset, line) in &lines {
+        let (Line::Code(instruction), Some(format)) = (line, overrides.format_at(*offset)) else {
+            continue;
+        };
+        let Some((_, (value, is_wide))) = instruction.formattable() else {
+            eprintln!("warning: {offset:#x}: no immediate or address to format");
+            continue;
+        };
+        let target = usize::from(value);
+        // a label has to land on the start of a line, or inside data where one can be split
+        let placeable = lines.iter().any(|(start, line)| match line {
+            Line::Code(_) => *start == target,
+            Line::Data(range) => range.contains(&target),
+        });
+        let format = match format {
+            OperandFormat::Label if !is_wide || !placeable => {
+                eprintln!("warning: {offset:#x}: can't place a label at {value:#x}, using hex");
+                OperandFormat::Hex
+            }
+            OperandFormat::Label => {
+                labels.insert(target);
+                format
+            }
+            OperandFormat::Hex => format,
+        };
+        formats.insert(*offset, format);
+    }
+
+    for (offset, line) in lines {
+        match line {
+            Line::Code(instruction) => {
+                if labels.contains(&offset) {
+                    writeln!(out_file, "{}:", OperandFormat::label(offset))?;
+                }
+                match formats.get(&offset) {
+                    Some(format) => writeln!(out_file, "{}", instruction.formatted(*format))?,
+                    None => writeln!(out_file, "{instruction}")?,
+                }
+            }
+            Line::Data(range) => write_data(&mut out_file, range.start, &image[range], &labels)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// A line of disassembly output, before it's written.
+enum Line {
+    Code(Inst),
+    Data(Range<usize>),
+}
 
-    let infile_name = cli
-        .infile
+/// Writes `data`, which starts at `start` in the image, as `db` lines, breaking them where a label
+/// goes.
+fn write_data(
+    out: &mut impl Write,
+    start: usize,
+    data: &[u8],
+    labels: &BTreeSet<usize>,
+) -> anyhow::Result<()> {
+    let mut pos = 0;
+    while pos < data.len() {
+        if labels.contains(&(start + pos)) {
+            writeln!(out, "{}:", OperandFormat::label(start + pos))?;
+        }
+        let end = labels
+            .range(start + pos + 1..start + data.len())
+            .next()
+            .map_or(data.len(), |label| label - start)
+            .min(pos + 16);
+        let bytes: Vec<_> = data[pos..end].iter().map(|b| format!("{b:#04x}")).collect();
+        writeln!(out, "db {}", bytes.join(", "))?;
+        pos = end;
+    }
+    Ok(())
+}
+
+fn program_stream(image: &[u8]) -> ByteStream<Cursor<Vec<u8>>> {
+    ByteStream {
+        reader: BufReader::new(Cursor::new(image.to_vec())),
+    }
+}
+
+/// Stamps the format version, when one was asked for, at the top of the output.
+fn print_header(cli: &Cli) {
+    if let Some(version) = cli.format_version {
+        println!(";format-version: {version}");
+    }
+}
+
+/// Parses a decimal or hex address or port number.
+fn parse_word(s: &str) -> anyhow::Result<u16> {
+    parse_offset(s)
+        .and_then(|word| u16::try_from(word).ok())
+        .ok_or_else(|| anyhow!("invalid address: {s}"))
+}
+
+fn format_version(cli: &Cli) -> u32 {
+    cli.format_version.unwrap_or(FORMAT_VERSION)
+}
+
+fn infile_name(cli: &Cli) -> anyhow::Result<impl std::fmt::Display + '_> {
+    Ok(cli
+        .infile()?
         .file_name()
         .ok_or(anyhow!("invalid in file"))?
-        .display();
+        .display())
+}
 
-    if let Some(out_file_path) = cli.outfile {
-        let mut out_file = BufWriter::new(File::create(&out_file_path)?);
-        writeln!(out_file, ";{infile_name}")?;
-        writeln!(out_file)?;
-        writeln!(out_file, "bits 16")?;
-        writeln!(out_file)?;
+/// Simulates the program to completion, handing each line of the trace and final register
+/// report to `emit`.
+fn run(
+    cli: &Cli,
+    inputs: &Inputs,
+    emit: impl FnMut(String) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut computer = setup(cli, inputs)?;
+    let result = trace(cli, &mut computer, inputs, emit);
+    if let Some(path) = &cli.save_state {
+        computer.save_state()?.write_to(path)?;
+    }
+    result
+}
+
+/// Sets up a computer with the program and everything else the options ask to load.
+fn setup(cli: &Cli, inputs: &Inputs) -> anyhow::Result<Computer> {
+    let is_com = cli
+        .infile()?
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("com"));
+    let entry = FarPointer {
+        segment: cli.load_segment,
+        offset: if is_com { COM_ORIGIN } else { 0 },
+    };
+    let mut registers = cli.reg.clone();
+    if is_com {
+        // DOS leaves a zero word on top of the stack, so a final `ret` lands on the PSP's
+        // `int 20h` and ends the program
+        registers.insert(
+            0,
+            RegisterValue {
+                register: Register::SP,
+                value: 0xFFFE,
+            },
+        );
+    }
+    let mut computer = Computer::new(&inputs.image, entry, cli.print_ip)
+        .with_format_version(format_version(cli))
+        .with_flag_warnings(cli.warn_flags)
+        .with_watched_registers(cli.watch.clone())
+        .with_watched_memory(cli.watch_mem.clone())
+        .with_access_log(cli.memory_log.is_some())
+        .with_initial_registers(registers)
+        .with_clocks(if cli.show_clocks {
+            cli.cpu.clone()
+        } else {
+            vec![]
+        })
+        .with_stack_dump(cli.dump_stack)
+        .with_mnemonic_stats(cli.stats)
+        .with_single_step(cli.single_step)
+        .with_instruction_limit(cli.max_instructions);
+    let mut bus = DeviceBus::default();
+    if let Some(port) = cli.console_port {
+        bus = bus.with_device(ConsoleDevice::new(port), None);
+    }
+    if cli.pit {
+        bus = bus.with_device(Pit::default(), Some(0));
+    }
+    if let Some(target) = &cli.serial {
+        bus = bus.with_device(Uart::open(uart::COM1, target)?, Some(4));
+    }
+    if cli.pic {
+        bus = bus.with_pic(Pic::default());
+    }
+    computer = computer.with_io_bus(bus);
+    if is_com {
+        let psp = computer.physical_address(Register::CS, 0);
+        computer = computer.with_memory_image(psp, &[0xCD, 0x20]);
+    }
+    for (address, data) in &inputs.loads {
+        computer = computer.with_memory_image(*address, data);
+    }
+    if let Some(state) = &inputs.restored {
+        computer.restore_state(state.clone());
+    }
+    Ok(computer)
+}
 
-        while let Some(instruction) = Inst::parse(&mut byte_stream)? {
-            writeln!(out_file, "{instruction}")?;
+/// Runs `computer` until it halts, handing each line of the trace and final register report to
+/// `emit`.
+fn trace(
+    cli: &Cli,
+    computer: &mut Computer,
+    inputs: &Inputs,
+    mut emit: impl FnMut(String) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut memory_log = match &cli.memory_log {
+        Some(path) => Some(BufWriter::new(File::create(path)?)),
+        None => None,
+    };
+    let mut recent = VecDeque::with_capacity(RECENT_INSTRUCTIONS);
+    loop {
+        let ip = computer.ip();
+        if cli.breakpoints.contains(&ip) {
+            if io::stdin().is_terminal() {
+                eprintln!("break at ip {ip:#x}: {}", computer.snapshot());
+                eprint!("-- press Enter to continue --");
+                io::stdin().read_line(&mut String::new())?;
+            } else {
+                // A comment, so `--verify` and reassembly skip it like the other annotations
+                emit(format!("; break at ip {ip:#x}: {}", computer.snapshot()))?;
+            }
+        }
+        let result = match computer.execute_instruction() {
+            Ok(result) => result,
+            Err(e) => {
+                if let Some(path) = &cli.crash_bundle {
+                    CrashBundle {
+                        args: env::args().collect(),
+                        error: format!("{e:#}"),
+                        recent: recent.into(),
+                        state: computer.save_state()?,
+                        image: inputs.image.clone(),
+                        loads: inputs.loads.clone(),
+                        restored: inputs.restored.clone(),
+                    }
+                    .write_to(path)?;
+                    eprintln!("crash bundle written to {}", path.display());
+                }
+                return Err(e);
+            }
+        };
+        let ExeResult::Success(instruction, update) = result else {
+            break;
+        };
+        let line = format!("{instruction} ; {} ", update.print(cli.print_ip)?);
+        if recent.len() == RECENT_INSTRUCTIONS {
+            recent.pop_front();
+        }
+        recent.push_back(line.clone());
+        emit(line)?;
+        // Version 1 traces predate the checks, so there they go to stderr instead
+        if let Some(warning) = update.flag_warning() {
+            match format_version(cli) {
+                1 => eprintln!("warning: {warning}"),
+                _ => emit(format!("  warning: {warning}"))?,
+            }
+        }
+        for hit in update.watch_hits() {
+            emit(format!("  {hit}"))?;
+        }
+        if let Some(log) = &mut memory_log {
+            for access in update.accesses() {
+                writeln!(log, "{access}")?;
+            }
+        }
+        if update.single_stepped() {
+            eprint!("-- step: press Enter to continue --");
+            io::stdin().read_line(&mut String::new())?;
         }
+    }
+    if let Some(dump) = &cli.dump {
+        let range = dump.start..dump.end.unwrap_or(Memory::SIZE);
+        fs::write(&dump.path, computer.memory().region(range)?)?;
+    }
+    for line in computer.registers_report()?.lines() {
+        emit(line.to_string())?;
+    }
+    Ok(())
+}
 
-        return Ok(());
+/// Runs the program twice on the same computer, resetting it in between, and checks that both
+/// runs produce an identical trace.
+fn audit_determinism(cli: &Cli, inputs: &Inputs) -> anyhow::Result<()> {
+    print_header(cli);
+    let mut computer = setup(cli, inputs)?;
+    let mut runs = [vec![], vec![]];
+    for lines in &mut runs {
+        trace(cli, &mut computer, inputs, |line| {
+            lines.push(line);
+            Ok(())
+        })?;
+        computer.reset();
     }
+    let [first, second] = runs;
 
-    let mut computer = computer::Computer::new(byte_stream, cli.print_ip);
-    println!("--- test\\{infile_name} execution ---");
-    while let ExeResult::Success(instruction, update) = computer.execute_instruction()? {
-        println!("{instruction} ; {} ", update.print(cli.print_ip)?);
+    if let Some((ix, (a, b))) = first
+        .iter()
+        .zip(&second)
+        .enumerate()
+        .find(|(_, (a, b))| a != b)
+    {
+        return Err(anyhow!(
+            "nondeterministic execution at trace line {}:\n  first run:  {a}\n  second run: {b}",
+            ix + 1
+        ));
+    }
+    if first.len() != second.len() {
+        return Err(anyhow!(
+            "nondeterministic execution: first run produced {} trace lines, second run {}",
+            first.len(),
+            second.len()
+        ));
     }
 
+    println!(
+        "determinism audit passed: {} trace lines identical across two runs",
+        first.len()
+    );
     Ok(())
 }
+
+/// Replays the program stored in a crash bundle using the options it was recorded with. The
+/// replay only prints: every option that would write a file is dropped, and it runs from the
+/// bundle's copies of the program, loaded images and restored state.
+fn open_bundle(path: &Path) -> anyhow::Result<()> {
+    let bundle = CrashBundle::read_from(path)?;
+    let mut cli = Cli::try_parse_from(&bundle.args)?;
+    cli.outfile = None;
+    cli.crash_bundle = None;
+    cli.dump = None;
+    cli.save_state = None;
+    cli.memory_log = None;
+    cli.verify = None;
+    cli.load.clear();
+    cli.restore_state = None;
+
+    // Only to describe the recorded state; restoring it replaces everything `new` set up
+    let origin = FarPointer {
+        segment: 0,
+        offset: 0,
+    };
+    let mut recorded = Computer::new(&[], origin, cli.print_ip);
+    recorded.restore_state(bundle.state);
+    print_header(&cli);
+    println!("--- crash bundle {} ---", path.display());
+    println!("recorded error: {}", bundle.error);
+    println!("recorded state: {}", recorded.snapshot());
+    let inputs = Inputs {
+        image: bundle.image,
+        loads: bundle.loads,
+        restored: bundle.restored,
+    };
+    run(&cli, &inputs, |line| {
+        println!("{line}");
+        Ok(())
+    })
+}
@@ -0,0 +1,158 @@
+use crate::{data::create_word, overrides::parse_offset};
+use anyhow::anyhow;
+use std::{
+    fmt::{Debug, Display},
+    ops::Range,
+    path::PathBuf,
+    str::FromStr,
+};
+
+/// The 8086's 1 MiB physical address space, wrapping at 20 bits.
+pub(crate) struct Memory {
+    bytes: Vec<u8>,
+}
+
+impl Debug for Memory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Memory")
+            .field("size", &self.bytes.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Memory {
+    pub(crate) const SIZE: usize = 1 << 20;
+
+    pub(crate) fn new() -> Self {
+        Self {
+            bytes: vec![0; Self::SIZE],
+        }
+    }
+
+    pub(crate) fn read_byte(&self, address: u32) -> u8 {
+        self.bytes[wrap(address)]
+    }
+
+    pub(crate) fn write_byte(&mut self, address: u32, value: u8) {
+        self.bytes[wrap(address)] = value;
+    }
+
+    pub(crate) fn read_word(&self, address: u32) -> u16 {
+        create_word(self.read_byte(address), self.read_byte(address + 1))
+    }
+
+    pub(crate) fn write_word(&mut self, address: u32, value: u16) {
+        self.write_byte(address, value as u8);
+        self.write_byte(address + 1, (value >> 8) as u8);
+    }
+
+    pub(crate) fn read(&self, address: u32, is_wide: bool) -> u16 {
+        if is_wide {
+            self.read_word(address)
+        } else {
+            self.read_byte(address).into()
+        }
+    }
+
+    pub(crate) fn write(&mut self, address: u32, is_wide: bool, value: u16) {
+        if is_wide {
+            self.write_word(address, value)
+        } else {
+            self.write_byte(address, value as u8)
+        }
+    }
+
+    pub(crate) fn region(&self, range: Range<usize>) -> anyhow::Result<&[u8]> {
+        self.bytes
+            .get(range.clone())
+            .ok_or_else(|| anyhow!("{range:#x?} is outside the 1 MiB address space"))
+    }
+}
+
+/// A file tied to a region of memory, written as `FILE`, `FILE@START` or `FILE@START..END`.
+/// Without an end the region runs to the end of the file or of memory.
+#[derive(Debug, Clone)]
+pub(crate) struct MemoryFile {
+    pub(crate) path: PathBuf,
+    pub(crate) start: usize,
+    pub(crate) end: Option<usize>,
+}
+
+impl FromStr for MemoryFile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((path, region)) = s.rsplit_once('@') else {
+            return Ok(Self {
+                path: s.into(),
+                start: 0,
+                end: None,
+            });
+        };
+        let err = || anyhow!("invalid memory region: {region}");
+        let (start, end) = match region.split_once("..") {
+            Some((start, end)) => (start, Some(parse_offset(end).ok_or_else(err)?)),
+            None => (region, None),
+        };
+        Ok(Self {
+            path: path.into(),
+            start: parse_offset(start).ok_or_else(err)?,
+            end,
+        })
+    }
+}
+
+/// A range of physical addresses, written `START..END` or just `START` for a single byte.
+#[derive(Debug, Clone)]
+pub(crate) struct MemoryRange(pub(crate) Range<u32>);
+
+impl MemoryRange {
+    pub(crate) fn overlaps(&self, address: u32, is_wide: bool) -> bool {
+        let end = address + if is_wide { 2 } else { 1 };
+        address < self.0.end && self.0.start < end
+    }
+}
+
+impl FromStr for MemoryRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || anyhow!("invalid memory range: {s}");
+        let parse = |n| parse_offset(n).and_then(|n| u32::try_from(n).ok());
+        let range = match s.split_once("..") {
+            Some((start, end)) => parse(start).ok_or_else(err)?..parse(end).ok_or_else(err)?,
+            None => {
+                let start = parse(s).ok_or_else(err)?;
+                start..start + 1
+            }
+        };
+        Ok(Self(range))
+    }
+}
+
+/// One load or store the program made.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct MemoryAccess {
+    pub(crate) ip: u16,
+    pub(crate) address: u32,
+    pub(crate) is_wide: bool,
+    /// What a store overwrote; `None` for loads.
+    pub(crate) old: Option<u16>,
+    pub(crate) value: u16,
+}
+
+impl Display for MemoryAccess {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let width = if self.is_wide { "word" } else { "byte" };
+        write!(f, "[{:#x}] {width} ", self.address)?;
+        match self.old {
+            Some(old) => write!(f, "write {old:#x}->{:#x}", self.value)?,
+            None => write!(f, "read {:#x}", self.value)?,
+        }
+        write!(f, " at ip {:#x}", self.ip)
+    }
+}
+
+fn wrap(address: u32) -> usize {
+    address as usize % Memory::SIZE
+}
@@ -0,0 +1,115 @@
+use crate::instruction::OperandFormat;
+use anyhow::anyhow;
+use std::{fs, io::Write, ops::Range, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RegionKind {
+    Code,
+    Data,
+}
+
+/// User decisions about how to treat parts of an image, read from a plain text file with one
+/// directive per line:
+///
+/// ```text
+/// # comments start with a hash
+/// entry 0x10
+/// data 0x20..0x40
+/// code 0x4..0x8
+/// hex 0x4       # the immediate of the instruction at 0x4, written in hex
+/// address 0x6   # the same at 0x6, which is an address: written as a label placed there
+/// ```
+///
+/// Later region lines win over earlier ones where they overlap. `hex` and `address` apply to an
+/// instruction's immediate, or to its direct address when it has no immediate.
+#[derive(Debug, Default)]
+pub(crate) struct Overrides {
+    pub(crate) entry: Option<usize>,
+    regions: Vec<(Range<usize>, RegionKind)>,
+    formats: Vec<(usize, OperandFormat)>,
+}
+
+impl Overrides {
+    pub(crate) fn read_from(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let mut overrides = Self::default();
+        for (ix, line) in fs::read_to_string(path)?.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let err = || anyhow!("{}:{}: invalid override: {line}", path.display(), ix + 1);
+            let (directive, arg) = line.split_once(char::is_whitespace).ok_or_else(err)?;
+            let arg = arg.trim();
+            match directive {
+                "entry" => overrides.entry = Some(parse_offset(arg).ok_or_else(err)?),
+                "code" | "data" => {
+                    let (start, end) = arg.split_once("..").ok_or_else(err)?;
+                    let range =
+                        parse_offset(start).ok_or_else(err)?..parse_offset(end).ok_or_else(err)?;
+                    let kind = if directive == "code" {
+                        RegionKind::Code
+                    } else {
+                        RegionKind::Data
+                    };
+                    overrides.regions.push((range, kind));
+                }
+                "hex" | "address" => {
+                    let format = if directive == "hex" {
+                        OperandFormat::Hex
+                    } else {
+                        OperandFormat::Label
+                    };
+                    overrides
+                        .formats
+                        .push((parse_offset(arg).ok_or_else(err)?, format));
+                }
+                _ => return Err(err()),
+            }
+        }
+        Ok(overrides)
+    }
+
+    pub(crate) fn save_entry(path: &Path, entry: usize) -> anyhow::Result<()> {
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        writeln!(file, "entry {entry:#x}")?;
+        Ok(())
+    }
+
+    /// Whether the byte at `offset` should be decoded or emitted as data.
+    pub(crate) fn kind_at(&self, offset: usize, entry: usize) -> RegionKind {
+        self.regions
+            .iter()
+            .rev()
+            .find(|(range, _)| range.contains(&offset))
+            .map(|(_, kind)| *kind)
+            .unwrap_or(if offset < entry {
+                RegionKind::Data
+            } else {
+                RegionKind::Code
+            })
+    }
+
+    /// How to write the operand of the instruction starting at `offset`.
+    pub(crate) fn format_at(&self, offset: usize) -> Option<OperandFormat> {
+        self.formats
+            .iter()
+            .rev()
+            .find(|(at, _)| *at == offset)
+            .map(|(_, format)| *format)
+    }
+}
+
+pub(crate) fn parse_offset(s: &str) -> Option<usize> {
+    let s = s.trim();
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
@@ -1,10 +1,11 @@
+use anyhow::anyhow;
 use std::io::Read;
 
 use crate::{
     ByteStream, Register,
-    data::{Data, DataArg, RelativeJump},
+    data::{Data, DataArg, FarPointer, RelativeJump, create_word},
     instruction::Operands,
-    target::{MemoryAddress, Target},
+    target::{FarMemory, MemoryAddress, SizedMemory, Target},
 };
 
 pub(crate) fn parse_reg_mem_either_way<T: Read>(
@@ -46,6 +47,41 @@ pub(crate) fn parse_imm_to_reg_mem<T: Read>(
     ))
 }
 
+pub(crate) fn parse_reg_mem<T: Read>(
+    byte_1: u8,
+    byte_2: u8,
+    bytes: &mut ByteStream<T>,
+) -> anyhow::Result<Operands> {
+    let is_wide = byte_1 & 0b1 == 1;
+    let operand = match Target::parse(bytes, byte_2, is_wide)? {
+        Target::Register(register) => register.into(),
+        Target::Memory(address) => SizedMemory { is_wide, address }.into(),
+    };
+    Ok((Some(operand), None))
+}
+
+pub(crate) fn parse_shift<T: Read>(
+    byte_1: u8,
+    byte_2: u8,
+    bytes: &mut ByteStream<T>,
+) -> anyhow::Result<Operands> {
+    let (target, _) = parse_reg_mem(byte_1, byte_2, bytes)?;
+    let count = if byte_1 & 0b10 != 0 {
+        Register::CL.into()
+    } else {
+        Data::Byte(1).into()
+    };
+    Ok((target, Some(count)))
+}
+
+pub(crate) fn parse_reg(byte_1: u8) -> anyhow::Result<Operands> {
+    Ok((Some(Register::from_reg(byte_1 & 0b111, true)?.into()), None))
+}
+
+pub(crate) fn parse_seg_reg(byte_1: u8) -> anyhow::Result<Operands> {
+    Ok((Some(Register::from_sr(byte_1 >> 3 & 0b11)?.into()), None))
+}
+
 pub(crate) fn parse_imm_to_acc<T: Read>(
     byte_1: u8,
     bytes: &mut ByteStream<T>,
@@ -61,13 +97,43 @@ pub(crate) fn parse_ip_inc_8(byte: u8) -> Operands {
     (
         Some(
             RelativeJump {
-                offset: byte as i8 + 2,
+                offset: byte as i8 as i16 + 2,
+                near: false,
             }
             .into(),
         ),
         None,
     )
 }
+
+pub(crate) fn parse_ip_inc_16<T: Read>(
+    bytes: &mut ByteStream<T>,
+    near: bool,
+) -> anyhow::Result<Operands> {
+    let offset = create_word(bytes.next()?, bytes.next()?) as i16;
+    Ok((
+        Some(
+            RelativeJump {
+                offset: offset.wrapping_add(3),
+                near,
+            }
+            .into(),
+        ),
+        None,
+    ))
+}
+
+/// `aam` and `aad` carry the number base as an operand byte, only printed when it isn't the
+/// usual base 10.
+pub(crate) fn parse_ascii_adjust_base(byte: u8) -> Operands {
+    let base = (byte != 10).then(|| Data::Byte(byte).into());
+    (base, None)
+}
+
+pub(crate) fn parse_imm16<T: Read>(bytes: &mut ByteStream<T>) -> anyhow::Result<Operands> {
+    Ok((Some(Data::parse(bytes, true, false)?.into()), None))
+}
+
 pub(crate) fn parse_mov_imm_to_reg<T: Read>(
     byte_1: u8,
     bytes: &mut ByteStream<T>,
@@ -84,8 +150,8 @@ pub(crate) fn parse_mov_acc_to_mem<T: Read>(
     bytes: &mut ByteStream<T>,
 ) -> anyhow::Result<Operands> {
     Ok((
-        Some(parse_mem(byte_1, bytes)?.into()),
-        Some(Register::AX.into()),
+        Some(parse_mem(bytes)?.into()),
+        Some(accumulator(byte_1).into()),
     ))
 }
 
@@ -94,17 +160,37 @@ pub(crate) fn parse_mov_mem_to_acc<T: Read>(
     bytes: &mut ByteStream<T>,
 ) -> anyhow::Result<Operands> {
     Ok((
-        Some(Register::AX.into()),
-        Some(parse_mem(byte_1, bytes)?.into()),
+        Some(accumulator(byte_1).into()),
+        Some(parse_mem(bytes)?.into()),
     ))
 }
 
-fn parse_mem<T: Read>(byte_1: u8, bytes: &mut ByteStream<T>) -> anyhow::Result<MemoryAddress> {
-    Ok(MemoryAddress::Direct(Data::parse(
-        bytes,
-        (byte_1 & 0b1) == 1,
-        false,
-    )?))
+fn parse_mem<T: Read>(bytes: &mut ByteStream<T>) -> anyhow::Result<MemoryAddress> {
+    Ok(MemoryAddress::Direct(Data::parse(bytes, true, false)?))
+}
+
+/// The accumulator and an 8-bit port number, in `in` order.
+pub(crate) fn parse_port_fixed<T: Read>(
+    byte_1: u8,
+    bytes: &mut ByteStream<T>,
+) -> anyhow::Result<Operands> {
+    Ok((
+        Some(accumulator(byte_1).into()),
+        Some(Data::Byte(bytes.next()?).into()),
+    ))
+}
+
+/// The accumulator and the port in DX, in `in` order.
+pub(crate) fn parse_port_variable(byte_1: u8) -> Operands {
+    (Some(accumulator(byte_1).into()), Some(Register::DX.into()))
+}
+
+fn accumulator(byte_1: u8) -> Register {
+    if byte_1 & 0b1 == 1 {
+        Register::AX
+    } else {
+        Register::AL
+    }
 }
 
 pub(crate) fn parse_sm_to_rm<T: Read>(bytes: &mut ByteStream<T>) -> anyhow::Result<Operands> {
@@ -119,3 +205,43 @@ pub(crate) fn parse_rm_to_sm<T: Read>(bytes: &mut ByteStream<T>) -> anyhow::Resu
     let (a, b) = parse_sm_to_rm(bytes)?;
     Ok((b, a))
 }
+
+/// A word register and a memory operand, as taken by `lea`, `lds` and `les`.
+pub(crate) fn parse_lea<T: Read>(bytes: &mut ByteStream<T>) -> anyhow::Result<Operands> {
+    let byte_2 = bytes.next()?;
+    let reg = Register::from_reg((byte_2 >> 3) & 0b111, true)?;
+    let Target::Memory(mem) = Target::parse(bytes, byte_2, true)? else {
+        return Err(anyhow!(
+            "lea, lds and les require a memory operand, got mod-r/m: {byte_2:08b}"
+        ));
+    };
+    Ok((Some(reg.into()), Some(mem.into())))
+}
+
+pub(crate) fn parse_far_ptr<T: Read>(bytes: &mut ByteStream<T>) -> anyhow::Result<Operands> {
+    let offset = create_word(bytes.next()?, bytes.next()?);
+    let segment = create_word(bytes.next()?, bytes.next()?);
+    Ok((Some(FarPointer { segment, offset }.into()), None))
+}
+
+pub(crate) fn parse_far_mem<T: Read>(
+    byte_2: u8,
+    bytes: &mut ByteStream<T>,
+) -> anyhow::Result<Operands> {
+    let Target::Memory(mem) = Target::parse(bytes, byte_2, true)? else {
+        return Err(anyhow!(
+            "far transfer requires a memory operand, got mod-r/m: {byte_2:08b}"
+        ));
+    };
+    Ok((Some(FarMemory(mem).into()), None))
+}
+
+pub(crate) fn parse_esc<T: Read>(
+    byte_1: u8,
+    bytes: &mut ByteStream<T>,
+) -> anyhow::Result<Operands> {
+    let byte_2 = bytes.next()?;
+    let opcode = (byte_1 & 0b111) << 3 | (byte_2 >> 3 & 0b111);
+    let source = Target::parse(bytes, byte_2, true)?;
+    Ok((Some(Data::Byte(opcode).into()), Some(source.into())))
+}
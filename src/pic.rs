@@ -0,0 +1,156 @@
+use crate::iobus::Device;
+
+const COMMAND_PORT: u16 = 0x20;
+const DATA_PORT: u16 = 0x21;
+
+/// Where the controller is in the initialization sequence that ICW1 starts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Init {
+    #[default]
+    Done,
+    VectorBase {
+        cascade: bool,
+        icw4: bool,
+    },
+    Cascade {
+        icw4: bool,
+    },
+    Mode,
+}
+
+/// An 8259 interrupt controller on ports 0x20–0x21, set up the way the PC's BIOS leaves it:
+/// IRQ n on INT 8+n, nothing masked. There's only one controller; nothing is cascaded.
+#[derive(Debug)]
+pub(crate) struct Pic {
+    vector_base: u8,
+    requested: u8,
+    in_service: u8,
+    mask: u8,
+    /// End interrupts as they're delivered instead of waiting for an EOI.
+    auto_eoi: bool,
+    /// Whether reads of the command port return the in-service register rather than requests.
+    read_in_service: bool,
+    init: Init,
+}
+
+impl Default for Pic {
+    fn default() -> Self {
+        Self {
+            vector_base: 8,
+            requested: 0,
+            in_service: 0,
+            mask: 0,
+            auto_eoi: false,
+            read_in_service: false,
+            init: Init::Done,
+        }
+    }
+}
+
+impl Pic {
+    pub(crate) fn request(&mut self, line: u8) {
+        self.requested |= 1 << line;
+    }
+
+    /// The vector for the highest-priority request not outranked by one in service, if any.
+    pub(crate) fn acknowledge(&mut self) -> Option<u8> {
+        let ready = self.requested & !self.mask;
+        if ready == 0 {
+            return None;
+        }
+        let line = ready.trailing_zeros();
+        // Anything in service at the same or a higher priority holds it back
+        if self.in_service != 0 && self.in_service.trailing_zeros() <= line {
+            return None;
+        }
+        self.requested &= !(1 << line);
+        if !self.auto_eoi {
+            self.in_service |= 1 << line;
+        }
+        Some(self.vector_base + line as u8)
+    }
+
+    pub(crate) fn end_vector(&mut self, vector: u8) {
+        if let Some(line) = vector
+            .checked_sub(self.vector_base)
+            .filter(|&line| line < 8)
+        {
+            self.in_service &= !(1 << line);
+        }
+    }
+
+    /// OCW2. Priority rotation isn't modelled, so rotating EOIs act like plain ones.
+    fn end_of_interrupt(&mut self, command: u8) {
+        match command >> 5 {
+            // Non-specific: ends the highest-priority interrupt in service
+            0b001 | 0b101 => self.in_service &= self.in_service.wrapping_sub(1),
+            // Specific: ends the interrupt on the given line
+            0b011 | 0b111 => self.in_service &= !(1 << (command & 0b111)),
+            _ => {}
+        }
+    }
+}
+
+impl Device for Pic {
+    fn claims(&self, port: u16) -> bool {
+        port == COMMAND_PORT || port == DATA_PORT
+    }
+
+    fn port_in(&mut self, port: u16) -> u8 {
+        match port {
+            DATA_PORT => self.mask,
+            _ if self.read_in_service => self.in_service,
+            _ => self.requested,
+        }
+    }
+
+    fn port_out(&mut self, port: u16, byte: u8) {
+        if port == COMMAND_PORT {
+            match byte {
+                // ICW1 restarts initialization
+                _ if byte & 0x10 != 0 => {
+                    *self = Self {
+                        init: Init::VectorBase {
+                            cascade: byte & 0b10 == 0,
+                            icw4: byte & 0b1 != 0,
+                        },
+                        ..Self::default()
+                    }
+                }
+                // OCW3: choose which register the command port reads back
+                _ if byte & 0x08 != 0 => {
+                    if byte & 0b10 != 0 {
+                        self.read_in_service = byte & 0b1 != 0;
+                    }
+                }
+                _ => self.end_of_interrupt(byte),
+            }
+            return;
+        }
+        self.init = match self.init {
+            Init::VectorBase { cascade, icw4 } => {
+                self.vector_base = byte & 0xF8;
+                match (cascade, icw4) {
+                    (true, _) => Init::Cascade { icw4 },
+                    (false, true) => Init::Mode,
+                    (false, false) => Init::Done,
+                }
+            }
+            Init::Cascade { icw4: true } => Init::Mode,
+            Init::Cascade { icw4: false } => Init::Done,
+            Init::Mode => {
+                self.auto_eoi = byte & 0b10 != 0;
+                Init::Done
+            }
+            // OCW1 sets the mask once initialized
+            Init::Done => {
+                self.mask = byte;
+                Init::Done
+            }
+        };
+    }
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
@@ -0,0 +1,168 @@
+use crate::iobus::Device;
+
+const COUNTER_PORTS: [u16; 3] = [0x40, 0x41, 0x42];
+const CONTROL_PORT: u16 = 0x43;
+
+/// CPU clocks per PIT input clock. The PC runs the CPU at 4.77 MHz and the PIT at 1.19 MHz.
+const CLOCK_DIVISOR: u32 = 4;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Access {
+    #[default]
+    Low,
+    High,
+    Both,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Channel {
+    mode: u8,
+    access: Access,
+    reload: u16,
+    /// Counts left until the output fires, from 1 to 65536.
+    count: u32,
+    counting: bool,
+    fired: bool,
+    latch: Option<u16>,
+    low_byte: Option<u8>,
+    read_high: bool,
+}
+
+impl Channel {
+    fn period(&self) -> u32 {
+        match self.reload {
+            0 => 0x10000,
+            reload => reload.into(),
+        }
+    }
+
+    fn load(&mut self) {
+        self.count = self.period();
+        self.counting = true;
+        self.fired = false;
+    }
+
+    fn advance(&mut self, ticks: u32) -> bool {
+        if !self.counting || ticks == 0 {
+            return false;
+        }
+        match self.mode {
+            // Interrupt on terminal count and software strobe: fire once, then keep wrapping
+            0 | 4 => {
+                let reached = ticks >= self.count;
+                self.count = (self.count + 0x10000 - ticks % 0x10000 - 1) % 0x10000 + 1;
+                let fire = reached && !self.fired;
+                self.fired |= reached;
+                fire
+            }
+            // Rate generator and square wave: reload and fire every period. The square wave's
+            // halves aren't modelled, so it reads back like a rate generator.
+            2 | 3 => {
+                let reached = ticks >= self.count;
+                let remaining = (ticks.saturating_sub(self.count)) % self.period();
+                self.count = if reached {
+                    self.period() - remaining
+                } else {
+                    self.count - ticks
+                };
+                reached
+            }
+            // The other modes wait on the gate input, which nothing drives
+            _ => false,
+        }
+    }
+
+    fn read(&mut self) -> u8 {
+        let value = self.latch.unwrap_or((self.count % 0x10000) as u16);
+        let (byte, done) = match self.access {
+            Access::Low => (value as u8, true),
+            Access::High => ((value >> 8) as u8, true),
+            Access::Both if !self.read_high => (value as u8, false),
+            Access::Both => ((value >> 8) as u8, true),
+        };
+        self.read_high = !done;
+        if done {
+            self.latch = None;
+        }
+        byte
+    }
+
+    fn write(&mut self, byte: u8) {
+        match (self.access, self.low_byte.take()) {
+            (Access::Low, _) => self.reload = byte.into(),
+            (Access::High, _) => self.reload = (byte as u16) << 8,
+            (Access::Both, None) => {
+                self.low_byte = Some(byte);
+                return;
+            }
+            (Access::Both, Some(low)) => self.reload = (byte as u16) << 8 | low as u16,
+        }
+        self.load();
+    }
+}
+
+/// An 8253 interval timer on ports 0x40–0x43. Channel 0 drives the interrupt line.
+#[derive(Debug, Default)]
+pub(crate) struct Pit {
+    channels: [Channel; 3],
+    spare_clocks: u32,
+}
+
+impl Device for Pit {
+    fn claims(&self, port: u16) -> bool {
+        COUNTER_PORTS.contains(&port) || port == CONTROL_PORT
+    }
+
+    fn port_in(&mut self, port: u16) -> u8 {
+        match COUNTER_PORTS.iter().position(|&p| p == port) {
+            Some(channel) => self.channels[channel].read(),
+            // The control port is write-only
+            None => 0xFF,
+        }
+    }
+
+    fn port_out(&mut self, port: u16, byte: u8) {
+        if let Some(channel) = COUNTER_PORTS.iter().position(|&p| p == port) {
+            self.channels[channel].write(byte);
+            return;
+        }
+        // Channel select 3 is the 8254's read-back command, which the 8253 doesn't have
+        let Some(channel) = self.channels.get_mut((byte >> 6) as usize) else {
+            return;
+        };
+        let access = match byte >> 4 & 0b11 {
+            0 => {
+                channel.latch = Some((channel.count % 0x10000) as u16);
+                return;
+            }
+            1 => Access::Low,
+            2 => Access::High,
+            _ => Access::Both,
+        };
+        *channel = Channel {
+            // Modes 6 and 7 are aliases for 2 and 3
+            mode: match byte >> 1 & 0b111 {
+                mode @ 6..=7 => mode - 4,
+                mode => mode,
+            },
+            access,
+            ..Channel::default()
+        };
+    }
+
+    fn tick(&mut self, clocks: u32) -> bool {
+        let clocks = self.spare_clocks + clocks;
+        self.spare_clocks = clocks % CLOCK_DIVISOR;
+        let ticks = clocks / CLOCK_DIVISOR;
+        let mut fired = false;
+        for (ix, channel) in self.channels.iter_mut().enumerate() {
+            // Only channel 0 is wired to an interrupt; the others still count for reads
+            fired |= channel.advance(ticks) && ix == 0;
+        }
+        fired
+    }
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
@@ -1,6 +1,7 @@
+use crate::overrides::parse_offset;
 use anyhow::anyhow;
-use enum_iterator::Sequence;
-use std::fmt::Display;
+use enum_iterator::{Sequence, all};
+use std::{fmt::Display, str::FromStr};
 
 #[derive(Debug)]
 pub(crate) enum RegType {
@@ -42,6 +43,16 @@ impl Display for Register {
     }
 }
 
+impl FromStr for Register {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        all::<Register>()
+            .find(|r| r.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| anyhow!("unknown register: {s}"))
+    }
+}
+
 impl Register {
     pub(crate) fn as_str(&self) -> &'static str {
         use Register::*;
@@ -122,6 +133,19 @@ impl Register {
         }
     }
 
+    pub(crate) fn is_wide(&self) -> bool {
+        matches!(self.get_type(), RegType::Wide)
+    }
+
+    /// Pulls this register's portion out of the full word stored in its register file slot.
+    pub(crate) fn extract(&self, word: u16) -> u16 {
+        match self.get_type() {
+            RegType::Low => word & 0b0000000011111111,
+            RegType::High => (word & 0b1111111100000000) >> 8,
+            RegType::Wide => word,
+        }
+    }
+
     pub(crate) fn get_reg_ix(&self) -> usize {
         use Register::*;
         match self {
@@ -140,3 +164,29 @@ impl Register {
         }
     }
 }
+
+/// A register and the value it starts with, written `REG=VALUE`, e.g. `sp=0xfffe`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RegisterValue {
+    pub(crate) register: Register,
+    pub(crate) value: u16,
+}
+
+impl FromStr for RegisterValue {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (register, value) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected REG=VALUE, got {s}"))?;
+        let register: Register = register.trim().parse()?;
+        let max = if register.is_wide() { 0xFFFF } else { 0xFF };
+        let value = parse_offset(value)
+            .filter(|&v| v <= max)
+            .ok_or_else(|| anyhow!("invalid value for {register}: {value}"))?;
+        Ok(Self {
+            register,
+            value: value as u16,
+        })
+    }
+}
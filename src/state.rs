@@ -0,0 +1,132 @@
+use crate::memory::Memory;
+use anyhow::anyhow;
+use std::{
+    fs,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+const HEADER: &str = ";i8086-decode machine state v1";
+
+/// Everything needed to pick a simulation up where it left off.
+#[derive(Debug, Clone)]
+pub(crate) struct MachineState {
+    pub(crate) registers: [u16; 12],
+    pub(crate) ip: u16,
+    pub(crate) flags: u16,
+    pub(crate) memory: Vec<u8>,
+    pub(crate) instructions_executed: u64,
+    pub(crate) stack_top: u16,
+    pub(crate) console: Vec<u8>,
+    pub(crate) exit_code: Option<u8>,
+    pub(crate) halted: bool,
+}
+
+impl Default for MachineState {
+    fn default() -> Self {
+        Self {
+            registers: [0; 12],
+            ip: 0,
+            flags: 0,
+            memory: vec![0; Memory::SIZE],
+            instructions_executed: 0,
+            stack_top: 0,
+            console: vec![],
+            exit_code: None,
+            halted: false,
+        }
+    }
+}
+
+impl MachineState {
+    pub(crate) fn write_to(&self, path: &Path) -> anyhow::Result<()> {
+        let mut out = BufWriter::new(fs::File::create(path)?);
+        writeln!(out, "{HEADER}")?;
+        self.write(&mut out)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    pub(crate) fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        let registers: Vec<_> = self.registers.iter().map(|r| format!("{r:04x}")).collect();
+        writeln!(out, "registers: {}", registers.join(" "))?;
+        writeln!(out, "ip: {:04x}", self.ip)?;
+        writeln!(out, "flags: {:04x}", self.flags)?;
+        writeln!(out, "instructions: {}", self.instructions_executed)?;
+        writeln!(out, "stack-top: {:04x}", self.stack_top)?;
+        if let Some(code) = self.exit_code {
+            writeln!(out, "exit: {code}")?;
+        }
+        if self.halted {
+            writeln!(out, "halted:")?;
+        }
+        for chunk in self.console.chunks(16) {
+            writeln!(out, "console: {}", hex(chunk))?;
+        }
+        for (row, chunk) in self.memory.chunks(16).enumerate() {
+            if chunk.iter().any(|&b| b != 0) {
+                writeln!(out, "memory: {:05x} {}", row * 16, hex(chunk))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn read_from(path: &Path) -> anyhow::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let mut lines = contents.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(anyhow!("{} is not a machine state file", path.display()));
+        }
+        Self::parse(lines)
+    }
+
+    pub(crate) fn parse<'a>(lines: impl IntoIterator<Item = &'a str>) -> anyhow::Result<Self> {
+        let mut state = Self::default();
+        for line in lines {
+            let (key, value) = line
+                .split_once(": ")
+                .or_else(|| line.strip_suffix(':').map(|key| (key, "")))
+                .ok_or_else(|| anyhow!("malformed machine state line: {line}"))?;
+            match key {
+                "registers" => {
+                    for (slot, word) in state.registers.iter_mut().zip(value.split_whitespace()) {
+                        *slot = u16::from_str_radix(word, 16)?;
+                    }
+                }
+                "ip" => state.ip = u16::from_str_radix(value, 16)?,
+                "flags" => state.flags = u16::from_str_radix(value, 16)?,
+                "instructions" => state.instructions_executed = value.parse()?,
+                "stack-top" => state.stack_top = u16::from_str_radix(value, 16)?,
+                "exit" => state.exit_code = Some(value.parse()?),
+                "halted" => state.halted = true,
+                "console" => state.console.extend(bytes(value)?),
+                "memory" => {
+                    let (address, data) = value
+                        .split_once(' ')
+                        .ok_or_else(|| anyhow!("malformed memory row: {value}"))?;
+                    let address = usize::from_str_radix(address, 16)?;
+                    let data = bytes(data)?;
+                    state
+                        .memory
+                        .get_mut(address..address + data.len())
+                        .ok_or_else(|| anyhow!("memory row {address:#x} is out of range"))?
+                        .copy_from_slice(&data);
+                }
+                _ => return Err(anyhow!("unknown machine state section: {key}")),
+            }
+        }
+        Ok(state)
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let hex: Vec<_> = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    hex.join(" ")
+}
+
+fn bytes(hex: &str) -> anyhow::Result<Vec<u8>> {
+    Ok(hex
+        .split_whitespace()
+        .map(|byte| u8::from_str_radix(byte, 16))
+        .collect::<Result<_, _>>()?)
+}
@@ -20,7 +20,11 @@ impl Display for MemoryAddress {
             MemoryAddress::Direct(data) => write!(f, "[{data}]"),
             MemoryAddress::RegnReg(reg1, reg2) => write!(f, "[{reg1} + {reg2}]",),
             MemoryAddress::Reg(reg) => write!(f, "[{reg}]"),
+            MemoryAddress::RegnData(reg, data) if data.is_zero() => write!(f, "[{reg}]"),
             MemoryAddress::RegnData(reg, data) => write!(f, "[{reg} {data}]"),
+            MemoryAddress::RegnRegnData(reg1, reg2, data) if data.is_zero() => {
+                write!(f, "[{reg1} + {reg2}]")
+            }
             MemoryAddress::RegnRegnData(reg1, reg2, data) => {
                 write!(f, "[{reg1} + {reg2} {data}]")
             }
@@ -28,6 +32,31 @@ impl Display for MemoryAddress {
     }
 }
 
+/// A memory operand that carries its own size, for instructions where no other operand implies
+/// whether a byte or a word is accessed (e.g. `inc byte [bx]`).
+#[derive(Debug)]
+pub(crate) struct SizedMemory {
+    pub(crate) is_wide: bool,
+    pub(crate) address: MemoryAddress,
+}
+
+impl Display for SizedMemory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let size = if self.is_wide { "word" } else { "byte" };
+        write!(f, "{size} {}", self.address)
+    }
+}
+
+/// A memory operand holding a `segment:offset` pair to transfer control through.
+#[derive(Debug)]
+pub(crate) struct FarMemory(pub(crate) MemoryAddress);
+
+impl Display for FarMemory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "far {}", self.0)
+    }
+}
+
 #[derive(Debug)]
 pub(crate) enum Target {
     Register(Register),
@@ -65,43 +94,23 @@ impl Target {
             }));
         }
 
-        let displacement = if mod_val == 0b01 {
-            let d = bytes.next()?;
-            if d == 0 { None } else { Some(Byte(d)) }
+        // Kept even when zero: the CPU still adds it, and takes the clocks to
+        let disp = if mod_val == 0b01 {
+            Byte(bytes.next()?)
         } else {
-            let b1 = bytes.next()?;
-            let b2 = bytes.next()?;
-            if b1 == 0 && b2 == 0 {
-                None
-            } else {
-                Some(Displacement::to_word(b1, b2))
-            }
+            Displacement::to_word(bytes.next()?, bytes.next()?)
         };
 
-        let mem_address = if let Some(disp) = displacement {
-            match r_m {
-                0b000 => RegnRegnData(BX, SI, disp),
-                0b001 => RegnRegnData(BX, DI, disp),
-                0b010 => RegnRegnData(BP, SI, disp),
-                0b011 => RegnRegnData(BP, DI, disp),
-                0b100 => RegnData(SI, disp),
-                0b101 => RegnData(DI, disp),
-                0b110 => RegnData(BP, disp),
-                0b111 => RegnData(BX, disp),
-                _ => unreachable!(),
-            }
-        } else {
-            match r_m {
-                0b000 => RegnReg(BX, SI),
-                0b001 => RegnReg(BX, DI),
-                0b010 => RegnReg(BP, SI),
-                0b011 => RegnReg(BP, DI),
-                0b100 => Reg(SI),
-                0b101 => Reg(DI),
-                0b110 => Reg(BP),
-                0b111 => Reg(BX),
-                _ => unreachable!(),
-            }
+        let mem_address = match r_m {
+            0b000 => RegnRegnData(BX, SI, disp),
+            0b001 => RegnRegnData(BX, DI, disp),
+            0b010 => RegnRegnData(BP, SI, disp),
+            0b011 => RegnRegnData(BP, DI, disp),
+            0b100 => RegnData(SI, disp),
+            0b101 => RegnData(DI, disp),
+            0b110 => RegnData(BP, disp),
+            0b111 => RegnData(BX, disp),
+            _ => unreachable!(),
         };
 
         Ok(Self::Memory(mem_address))
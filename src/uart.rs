@@ -0,0 +1,211 @@
+use crate::iobus::Device;
+use std::{
+    collections::VecDeque,
+    fmt,
+    fs::OpenOptions,
+    io::{self, Read, Write},
+    path::Path,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+pub(crate) const COM1: u16 = 0x3F8;
+
+const LCR_DLAB: u8 = 1 << 7;
+const MCR_OUT2: u8 = 1 << 3;
+const MCR_LOOPBACK: u8 = 1 << 4;
+const IER_RECEIVED: u8 = 1 << 0;
+const IER_TRANSMIT_EMPTY: u8 = 1 << 1;
+const LSR_DATA_READY: u8 = 1 << 0;
+const LSR_TRANSMIT_EMPTY: u8 = 1 << 5 | 1 << 6;
+/// Clear to send, data set ready and carrier detect, as if a modem were online.
+const MSR_ONLINE: u8 = 1 << 4 | 1 << 5 | 1 << 7;
+const IIR_NONE: u8 = 0b001;
+const IIR_TRANSMIT_EMPTY: u8 = 0b010;
+const IIR_RECEIVED: u8 = 0b100;
+
+/// An 8250 UART wired to something on the host. Transfers are instant whatever the divisor says.
+pub(crate) struct Uart {
+    base: u16,
+    input: Receiver<u8>,
+    output: Box<dyn Write + Send>,
+    received: VecDeque<u8>,
+    divisor: u16,
+    interrupt_enable: u8,
+    line_control: u8,
+    modem_control: u8,
+    scratch: u8,
+    transmit_empty_pending: bool,
+    irq: bool,
+}
+
+impl fmt::Debug for Uart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Uart")
+            .field("base", &self.base)
+            .field("received", &self.received)
+            .field("divisor", &self.divisor)
+            .field("interrupt_enable", &self.interrupt_enable)
+            .field("line_control", &self.line_control)
+            .field("modem_control", &self.modem_control)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Uart {
+    /// Connects a UART at `base` to `target`: `stdio`, or a path such as a pty or FIFO.
+    pub(crate) fn open(base: u16, target: &Path) -> anyhow::Result<Self> {
+        if target == Path::new("stdio") {
+            return Ok(Self::new(base, io::stdin(), io::stdout()));
+        }
+        let file = OpenOptions::new().read(true).write(true).open(target)?;
+        Ok(Self::new(base, file.try_clone()?, file))
+    }
+
+    fn new(
+        base: u16,
+        mut reader: impl Read + Send + 'static,
+        writer: impl Write + Send + 'static,
+    ) -> Self {
+        let (sender, input) = mpsc::channel();
+        thread::spawn(move || {
+            let mut buffer = [0; 256];
+            // Stop at end of input, a read error, or once the UART is gone
+            while let Ok(len @ 1..) = reader.read(&mut buffer) {
+                if buffer[..len].iter().any(|&byte| sender.send(byte).is_err()) {
+                    break;
+                }
+            }
+        });
+        Self {
+            base,
+            input,
+            output: Box::new(writer),
+            received: VecDeque::new(),
+            divisor: 0,
+            interrupt_enable: 0,
+            line_control: 0,
+            modem_control: 0,
+            scratch: 0,
+            transmit_empty_pending: false,
+            irq: false,
+        }
+    }
+
+    fn dlab(&self) -> bool {
+        self.line_control & LCR_DLAB != 0
+    }
+
+    fn loopback(&self) -> bool {
+        self.modem_control & MCR_LOOPBACK != 0
+    }
+
+    fn poll(&mut self) {
+        if !self.loopback() {
+            self.received.extend(self.input.try_iter());
+        }
+    }
+
+    fn transmit(&mut self, byte: u8) {
+        if self.loopback() {
+            self.received.push_back(byte);
+        } else {
+            let _ = self
+                .output
+                .write_all(&[byte])
+                .and_then(|_| self.output.flush());
+        }
+        self.transmit_empty_pending = true;
+    }
+
+    fn identify(&self) -> u8 {
+        if self.interrupt_enable & IER_RECEIVED != 0 && !self.received.is_empty() {
+            IIR_RECEIVED
+        } else if self.interrupt_enable & IER_TRANSMIT_EMPTY != 0 && self.transmit_empty_pending {
+            IIR_TRANSMIT_EMPTY
+        } else {
+            IIR_NONE
+        }
+    }
+}
+
+impl Device for Uart {
+    fn claims(&self, port: u16) -> bool {
+        port.wrapping_sub(self.base) < 8
+    }
+
+    fn port_in(&mut self, port: u16) -> u8 {
+        self.poll();
+        match port.wrapping_sub(self.base) {
+            0 if self.dlab() => self.divisor as u8,
+            0 => self.received.pop_front().unwrap_or(0),
+            1 if self.dlab() => (self.divisor >> 8) as u8,
+            1 => self.interrupt_enable,
+            2 => {
+                let id = self.identify();
+                // Reading the transmit empty interrupt's identification clears it
+                if id == IIR_TRANSMIT_EMPTY {
+                    self.transmit_empty_pending = false;
+                }
+                id
+            }
+            3 => self.line_control,
+            4 => self.modem_control,
+            5 => {
+                LSR_TRANSMIT_EMPTY
+                    | if self.received.is_empty() {
+                        0
+                    } else {
+                        LSR_DATA_READY
+                    }
+            }
+            6 if self.loopback() => {
+                // The modem control outputs feed back into the status inputs
+                let mcr = self.modem_control;
+                (mcr & 0b10) << 3 | (mcr & 0b01) << 5 | (mcr & 0b100) << 4 | (mcr & 0b1000) << 4
+            }
+            6 => MSR_ONLINE,
+            _ => self.scratch,
+        }
+    }
+
+    fn port_out(&mut self, port: u16, byte: u8) {
+        match port.wrapping_sub(self.base) {
+            0 if self.dlab() => self.divisor = self.divisor & 0xFF00 | byte as u16,
+            0 => self.transmit(byte),
+            1 if self.dlab() => self.divisor = self.divisor & 0x00FF | (byte as u16) << 8,
+            1 => {
+                // Enabling the transmit empty interrupt with nothing to send raises it at once
+                if byte & IER_TRANSMIT_EMPTY != 0 && self.interrupt_enable & IER_TRANSMIT_EMPTY == 0
+                {
+                    self.transmit_empty_pending = true;
+                }
+                self.interrupt_enable = byte & 0x0F;
+            }
+            3 => self.line_control = byte,
+            4 => self.modem_control = byte & 0x1F,
+            7 => self.scratch = byte,
+            // The 8250 has no FIFO control register, and the status registers are read-only
+            _ => {}
+        }
+    }
+
+    fn reset(&mut self) {
+        self.received.clear();
+        self.divisor = 0;
+        self.interrupt_enable = 0;
+        self.line_control = 0;
+        self.modem_control = 0;
+        self.scratch = 0;
+        self.transmit_empty_pending = false;
+        self.irq = false;
+    }
+
+    fn tick(&mut self, _clocks: u32) -> bool {
+        self.poll();
+        let irq = self.modem_control & MCR_OUT2 != 0 && self.identify() != IIR_NONE;
+        let raised = irq && !self.irq;
+        self.irq = irq;
+        raised
+    }
+}
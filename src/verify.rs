@@ -0,0 +1,61 @@
+use anyhow::anyhow;
+use std::{fs, path::Path};
+
+/// A known-good trace, such as a course listing, to check the simulator's output against.
+#[derive(Debug)]
+pub(crate) struct ReferenceTrace {
+    name: String,
+    lines: Vec<(usize, String)>,
+    next: usize,
+}
+
+impl ReferenceTrace {
+    pub(crate) fn read_from(path: &Path) -> anyhow::Result<Self> {
+        let lines = fs::read_to_string(path)?
+            .lines()
+            .enumerate()
+            .map(|(ix, line)| (ix + 1, line.trim_end().to_string()))
+            .filter(|(_, line)| !is_skipped(line))
+            .collect();
+        Ok(Self {
+            name: path.display().to_string(),
+            lines,
+            next: 0,
+        })
+    }
+
+    pub(crate) fn check(&mut self, actual: &str) -> anyhow::Result<()> {
+        let actual = actual.trim_end();
+        if is_skipped(actual) {
+            return Ok(());
+        }
+        let Some((number, expected)) = self.lines.get(self.next) else {
+            return Err(anyhow!(
+                "trace continues past the end of {}:\n  actual: {actual}",
+                self.name
+            ));
+        };
+        if expected != actual {
+            return Err(anyhow!(
+                "trace diverges from {} at line {number}:\n  expected: {expected}\n  actual:   {actual}",
+                self.name
+            ));
+        }
+        self.next += 1;
+        Ok(())
+    }
+
+    pub(crate) fn finish(&self) -> anyhow::Result<usize> {
+        match self.lines.get(self.next) {
+            Some((number, expected)) => Err(anyhow!(
+                "trace ended early; {} continues at line {number}:\n  expected: {expected}",
+                self.name
+            )),
+            None => Ok(self.next),
+        }
+    }
+}
+
+fn is_skipped(line: &str) -> bool {
+    line.is_empty() || line.starts_with("--- ") || line.starts_with(';')
+}